@@ -0,0 +1,917 @@
+use crate::action::{CharAction, Look};
+use crate::area::{RoomId, WorldId};
+use crate::creation::{CharacterWizard, CreationRules};
+use crate::error::GameError;
+use crate::game::{Game, GameEvent, PlayerId};
+use crate::snapshot::{CharacterSnapshot, build_character, capture_character};
+use crate::socium::CharacterId;
+use crate::utils::BuildRef;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const PBKDF2_ROUNDS: u32 = 20_000;
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+
+const MIN_PASSWORD_LENGTH: usize = 6;
+const MIN_NAME_LENGTH: usize = 3;
+const MAX_NAME_LENGTH: usize = 16;
+const MAX_PASSWORD_ATTEMPTS: u32 = 3;
+
+/// Someone who can log in, with the characters they play.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Account {
+    name: String,
+    salt: String,
+    password_hash: String,
+    /// Kept with the hash so that old accounts still log in if the default changes.
+    #[serde(default = "Account::default_rounds")]
+    rounds: u32,
+    #[serde(default)]
+    characters: Vec<SavedCharacter>,
+}
+
+/// A character of an account together with the world they were last in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedCharacter {
+    pub name: String,
+    pub world: u32,
+    pub character: CharacterSnapshot,
+}
+
+impl Account {
+    pub fn new(name: String, password: &str) -> Self {
+        let salt: [u8; SALT_LENGTH] = rand::random();
+
+        Self {
+            name,
+            salt: to_hex(&salt),
+            password_hash: to_hex(&hash_password(password, &salt, PBKDF2_ROUNDS)),
+            rounds: PBKDF2_ROUNDS,
+            characters: Vec::new(),
+        }
+    }
+
+    fn default_rounds() -> u32 {
+        PBKDF2_ROUNDS
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn check_password(&self, password: &str) -> bool {
+        let (Some(salt), Some(expected)) = (from_hex(&self.salt), from_hex(&self.password_hash))
+        else {
+            return false;
+        };
+
+        let hash = hash_password(password, &salt, self.rounds);
+
+        // Compare every byte whatever the first difference, so the time tells nothing.
+        expected.len() == hash.len()
+            && expected
+                .iter()
+                .zip(hash.iter())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    pub fn get_characters(&self) -> &[SavedCharacter] {
+        &self.characters
+    }
+
+    pub fn get_character(&self, name: &str) -> Option<&SavedCharacter> {
+        self.characters
+            .iter()
+            .find(|saved| saved.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Replaces the saved character of the same name, or adds a new one.
+    pub fn save_character(&mut self, saved: SavedCharacter) {
+        match self
+            .characters
+            .iter_mut()
+            .find(|c| c.name.eq_ignore_ascii_case(&saved.name))
+        {
+            Some(existing) => *existing = saved,
+            None => self.characters.push(saved),
+        }
+    }
+}
+
+fn hash_password(password: &str, salt: &[u8], rounds: u32) -> [u8; HASH_LENGTH] {
+    let mut hash = [0; HASH_LENGTH];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, rounds, &mut hash);
+
+    hash
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Store
+// ----------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct AccountsFile {
+    accounts: Vec<Account>,
+}
+
+/// Writes like an `AccountsFile` without copying the accounts.
+#[derive(Serialize)]
+struct AccountsFileRef<'a> {
+    accounts: Vec<&'a Account>,
+}
+
+/// The accounts, kept in a file which is rewritten whenever one of them changes.
+pub struct AccountStore {
+    path: PathBuf,
+    accounts: BTreeMap<String, Account>,
+}
+
+impl AccountStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the accounts from the file. A missing file means nobody has registered yet.
+    pub fn load(path: &Path) -> Result<Self, AccountError> {
+        let mut store = Self::new(path.to_path_buf());
+        if !path.exists() {
+            return Ok(store);
+        }
+
+        let text = fs::read_to_string(path).map_err(|error| AccountError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let file: AccountsFile = ron::from_str(&text).map_err(|error| AccountError::Parse {
+            path: path.to_path_buf(),
+            error: Box::new(error),
+        })?;
+
+        for account in file.accounts {
+            store.add_account(account)?;
+        }
+
+        Ok(store)
+    }
+
+    pub fn save(&self) -> Result<(), AccountError> {
+        let file = AccountsFileRef {
+            accounts: self.accounts.values().collect(),
+        };
+        let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(AccountError::Serialize)?;
+
+        let io_error = |error| AccountError::Io {
+            path: self.path.clone(),
+            error,
+        };
+
+        // Write aside and rename so that a crash in the middle never loses the accounts.
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, text).map_err(io_error)?;
+        fs::rename(&tmp_path, &self.path).map_err(io_error)
+    }
+
+    /// Account names do not depend on the case.
+    pub fn get_account(&self, name: &str) -> Option<&Account> {
+        self.accounts.get(&name.to_lowercase())
+    }
+
+    pub fn get_account_mut(&mut self, name: &str) -> Option<&mut Account> {
+        self.accounts.get_mut(&name.to_lowercase())
+    }
+
+    pub fn add_account(&mut self, account: Account) -> Result<(), AccountError> {
+        let key = account.name.to_lowercase();
+        if self.accounts.contains_key(&key) {
+            return Err(AccountError::DuplicateAccount(account.name));
+        }
+        self.accounts.insert(key, account);
+
+        Ok(())
+    }
+
+    /// Whether a character of any account goes by the name.
+    pub fn is_character_name_taken(&self, name: &str) -> bool {
+        self.accounts
+            .values()
+            .any(|account| account.get_character(name).is_some())
+    }
+}
+
+impl BuildRef for AccountStore {
+    fn build_ref(self) -> AccountStoreRef {
+        Rc::new(RefCell::new(self))
+    }
+}
+
+pub type AccountStoreRef = Rc<RefCell<AccountStore>>;
+
+#[derive(Debug)]
+pub enum AccountError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: Box<ron::error::SpannedError>,
+    },
+    Serialize(ron::Error),
+    DuplicateAccount(String),
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            AccountError::Parse { path, error } => {
+                write!(f, "cannot parse {}: {}", path.display(), error)
+            }
+            AccountError::Serialize(error) => write!(f, "cannot serialize the accounts: {}", error),
+            AccountError::DuplicateAccount(name) => {
+                write!(f, "account '{}' is defined more than once", name)
+            }
+        }
+    }
+}
+
+impl Error for AccountError {}
+
+// ----------------------------------------------------------------------------------------------------
+// Login
+// ----------------------------------------------------------------------------------------------------
+
+enum LoginState {
+    AskName,
+    AskPassword {
+        account: String,
+        attempts: u32,
+    },
+    ConfirmNewAccount {
+        name: String,
+    },
+    NewPassword {
+        name: String,
+    },
+    RepeatPassword {
+        name: String,
+        password: String,
+    },
+    ChooseCharacter {
+        account: String,
+    },
+    CreateCharacter {
+        account: String,
+        wizard: Box<CharacterWizard>,
+    },
+    Playing {
+        account: String,
+    },
+}
+
+/// Walks a connection through logging in or registering and choosing a character. The input
+/// goes to the game as commands only once a character is in play.
+pub struct Login {
+    state: LoginState,
+    start_world: WorldId,
+    creation: Rc<CreationRules>,
+}
+
+impl Login {
+    pub fn new(start_world: WorldId, creation: Rc<CreationRules>) -> Self {
+        Self {
+            state: LoginState::AskName,
+            start_world,
+            creation,
+        }
+    }
+
+    pub fn greet(&self, game: &mut Game, player_id: PlayerId) {
+        game.send_to_player(player_id, "Welcome to the MUD!");
+        game.send_to_player(player_id, "By what name do you go?");
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, LoginState::Playing { .. })
+    }
+
+    /// Whether the next line is a password, which the client should not show as it is typed.
+    pub fn is_asking_password(&self) -> bool {
+        matches!(
+            self.state,
+            LoginState::AskPassword { .. }
+                | LoginState::NewPassword { .. }
+                | LoginState::RepeatPassword { .. }
+        )
+    }
+
+    /// The account the connection has logged into, if any.
+    pub fn get_account(&self) -> Option<&str> {
+        match &self.state {
+            LoginState::ChooseCharacter { account }
+            | LoginState::CreateCharacter { account, .. }
+            | LoginState::Playing { account } => Some(account),
+            _ => None,
+        }
+    }
+
+    /// Moves the login one step on. `online` are the accounts other connections are logged into.
+    pub fn handle(
+        &mut self,
+        game: &mut Game,
+        accounts: &mut AccountStore,
+        online: &HashSet<String>,
+        player_id: PlayerId,
+        input: &str,
+    ) {
+        let input = input.trim();
+        let state = std::mem::replace(&mut self.state, LoginState::AskName);
+
+        self.state = match state {
+            LoginState::AskName => Self::ask_name(game, accounts, player_id, input),
+            LoginState::AskPassword { account, attempts } => {
+                Self::ask_password(game, accounts, online, player_id, account, attempts, input)
+            }
+            LoginState::ConfirmNewAccount { name } => {
+                if input.to_lowercase().starts_with('y') {
+                    game.send_to_player(player_id, "Choose a password:");
+                    LoginState::NewPassword { name }
+                } else {
+                    game.send_to_player(player_id, "By what name do you go, then?");
+                    LoginState::AskName
+                }
+            }
+            LoginState::NewPassword { name } => {
+                if input.chars().count() < MIN_PASSWORD_LENGTH {
+                    let text = format!(
+                        "The password needs at least {} characters. Choose a password:",
+                        MIN_PASSWORD_LENGTH
+                    );
+                    game.send_to_player(player_id, &text);
+                    LoginState::NewPassword { name }
+                } else {
+                    game.send_to_player(player_id, "Repeat the password:");
+                    LoginState::RepeatPassword {
+                        name,
+                        password: input.to_string(),
+                    }
+                }
+            }
+            LoginState::RepeatPassword { name, password } => {
+                Self::register(game, accounts, player_id, name, password, input)
+            }
+            LoginState::ChooseCharacter { account } => {
+                self.choose_character(game, accounts, player_id, account, input)
+            }
+            LoginState::CreateCharacter { account, wizard } => {
+                self.create_character(game, accounts, player_id, account, wizard, input)
+            }
+            // The server passes the input of a playing connection to the game instead.
+            playing @ LoginState::Playing { .. } => playing,
+        };
+
+        // The autosave finds the account of the character through the player.
+        if let LoginState::Playing { account } = &self.state
+            && let Some(player) = game.get_player(player_id)
+        {
+            player.borrow_mut().set_account(account.clone());
+        }
+    }
+
+    fn ask_name(
+        game: &mut Game,
+        accounts: &AccountStore,
+        player_id: PlayerId,
+        input: &str,
+    ) -> LoginState {
+        let Some(name) = normalize_name(input) else {
+            send_name_rules(game, player_id);
+            return LoginState::AskName;
+        };
+
+        match accounts.get_account(&name) {
+            Some(account) => {
+                game.send_to_player(player_id, "Password:");
+                LoginState::AskPassword {
+                    account: account.get_name().to_string(),
+                    attempts: 0,
+                }
+            }
+            None => {
+                let text = format!("There is no account '{}'. Create it? (yes/no)", name);
+                game.send_to_player(player_id, &text);
+                LoginState::ConfirmNewAccount { name }
+            }
+        }
+    }
+
+    fn ask_password(
+        game: &mut Game,
+        accounts: &AccountStore,
+        online: &HashSet<String>,
+        player_id: PlayerId,
+        account: String,
+        attempts: u32,
+        input: &str,
+    ) -> LoginState {
+        let valid = accounts
+            .get_account(&account)
+            .is_some_and(|a| a.check_password(input));
+
+        if !valid {
+            let attempts = attempts + 1;
+            if attempts >= MAX_PASSWORD_ATTEMPTS {
+                game.send_to_player(player_id, "Wrong password. Goodbye!");
+                game.end_session(player_id);
+                return LoginState::AskName;
+            }
+            game.send_to_player(player_id, "Wrong password. Password:");
+            return LoginState::AskPassword { account, attempts };
+        }
+
+        if online.contains(&account.to_lowercase()) {
+            game.send_to_player(player_id, "Someone is playing on that account already.");
+            game.send_to_player(player_id, "By what name do you go?");
+            return LoginState::AskName;
+        }
+
+        send_character_menu(game, accounts, player_id, &account);
+        LoginState::ChooseCharacter { account }
+    }
+
+    fn register(
+        game: &mut Game,
+        accounts: &mut AccountStore,
+        player_id: PlayerId,
+        name: String,
+        password: String,
+        input: &str,
+    ) -> LoginState {
+        if input != password {
+            game.send_to_player(player_id, "The passwords differ. Choose a password:");
+            return LoginState::NewPassword { name };
+        }
+
+        // Someone else could have taken the name while this one typed the password.
+        if let Err(error) = accounts.add_account(Account::new(name.clone(), &password)) {
+            eprintln!(">>> {:?} cannot register: {error}", player_id);
+            game.send_to_player(player_id, "That name is taken. By what name do you go?");
+            return LoginState::AskName;
+        }
+        save_accounts(accounts);
+
+        game.send_to_player(player_id, &format!("Welcome, {}!", name));
+        send_character_menu(game, accounts, player_id, &name);
+        LoginState::ChooseCharacter { account: name }
+    }
+
+    fn choose_character(
+        &self,
+        game: &mut Game,
+        accounts: &AccountStore,
+        player_id: PlayerId,
+        account_name: String,
+        input: &str,
+    ) -> LoginState {
+        let Some(account) = accounts.get_account(&account_name) else {
+            return LoginState::AskName;
+        };
+
+        if input.eq_ignore_ascii_case("new") {
+            let wizard = Box::new(CharacterWizard::new(self.creation.clone()));
+            wizard.start(game, player_id);
+            return LoginState::CreateCharacter {
+                account: account_name,
+                wizard,
+            };
+        }
+
+        let chosen = match input.parse::<usize>() {
+            Ok(number) => number
+                .checked_sub(1)
+                .and_then(|i| account.get_characters().get(i)),
+            Err(_) => account.get_character(input),
+        };
+        let Some(saved) = chosen else {
+            send_character_menu(game, accounts, player_id, &account_name);
+            return LoginState::ChooseCharacter {
+                account: account_name,
+            };
+        };
+
+        match self.enter_saved(game, player_id, saved) {
+            Ok(()) => LoginState::Playing {
+                account: account_name,
+            },
+            Err(error) => {
+                eprintln!(">>> {:?} cannot enter the game: {error}", player_id);
+                game.send_to_player(player_id, "That character cannot enter the game now.");
+                send_character_menu(game, accounts, player_id, &account_name);
+                LoginState::ChooseCharacter {
+                    account: account_name,
+                }
+            }
+        }
+    }
+
+    fn create_character(
+        &self,
+        game: &mut Game,
+        accounts: &mut AccountStore,
+        player_id: PlayerId,
+        account: String,
+        mut wizard: Box<CharacterWizard>,
+        input: &str,
+    ) -> LoginState {
+        // Players may not pass for the NPCs of the world they start in either.
+        let npc_names: Vec<String> = match game.get_world(self.start_world) {
+            Some(world) => world
+                .borrow()
+                .get_npc_templates()
+                .map(|template| template.name.to_lowercase())
+                .collect(),
+            None => Vec::new(),
+        };
+        let is_name_taken = |name: &str| {
+            accounts.is_character_name_taken(name) || npc_names.contains(&name.to_lowercase())
+        };
+
+        let Some(draft) = wizard.handle(game, player_id, &is_name_taken, input) else {
+            return LoginState::CreateCharacter { account, wizard };
+        };
+
+        // Someone else could have taken the name while this one chose the rest.
+        if accounts.is_character_name_taken(&draft.name) {
+            let text = format!("Someone took the name {} meanwhile, sorry.", draft.name);
+            game.send_to_player(player_id, &text);
+            let wizard = Box::new(CharacterWizard::new(self.creation.clone()));
+            wizard.start(game, player_id);
+            return LoginState::CreateCharacter { account, wizard };
+        }
+
+        let char = match game.require_world(self.start_world) {
+            Ok(world) => draft.build(world.borrow_mut().allocate_character_id()),
+            Err(error) => {
+                eprintln!(">>> {:?} cannot create a character: {error}", player_id);
+                game.send_to_player(player_id, "No character can be created now, sorry.");
+                return LoginState::CreateCharacter { account, wizard };
+            }
+        };
+
+        if let Err(error) = game.spawn_player_character(self.start_world, player_id, char) {
+            eprintln!(">>> {:?} cannot create a character: {error}", player_id);
+            game.send_to_player(player_id, "No character can be created now, sorry.");
+            return LoginState::CreateCharacter { account, wizard };
+        }
+
+        // Save the character right away so that the name is taken from now on.
+        save_character(game, accounts, &account, player_id);
+        enter_game(game, player_id);
+
+        LoginState::Playing { account }
+    }
+
+    /// Brings the character back into the world they left, or the starting one if it is gone.
+    fn enter_saved(
+        &self,
+        game: &mut Game,
+        player_id: PlayerId,
+        saved: &SavedCharacter,
+    ) -> Result<(), GameError> {
+        let world_id = match game.get_world(WorldId::from(saved.world)) {
+            Some(_) => WorldId::from(saved.world),
+            None => self.start_world,
+        };
+        let world = game.require_world(world_id)?.clone();
+
+        let char_id = {
+            let mut world = world.borrow_mut();
+
+            // Find the room first, so that a failure leaves no character behind in the world.
+            let room = match saved.character.room {
+                Some(room_id) => world.get_room(RoomId::from(room_id)).cloned(),
+                None => None,
+            };
+            let room = match room {
+                Some(room) => room,
+                None => world.get_spawn_room()?,
+            };
+
+            let char_id = world.allocate_character_id();
+            if let Err(error) = build_character(&mut world, char_id, &saved.character) {
+                world.despawn_character(char_id);
+                return Err(error);
+            }
+            world.teleport_character(char_id, &room);
+
+            char_id
+        };
+
+        game.set_player_character(world_id, player_id, char_id)?;
+
+        let char = world.borrow().get_character(char_id).cloned();
+        if let Some(char) = char
+            && let Some(room) = char.borrow().get_current_room().clone()
+        {
+            let text = format!("{} appears.", char.borrow().get_name());
+            game.send_to_room(&room, Some(player_id), &text);
+            game.publish(GameEvent::CharacterSpawned {
+                who: char.clone(),
+                room,
+            });
+        }
+
+        enter_game(game, player_id);
+
+        Ok(())
+    }
+}
+
+/// Writes the character the player plays into the account. Called when they leave and when the
+/// character is created.
+pub fn save_character(
+    game: &Game,
+    accounts: &mut AccountStore,
+    account: &str,
+    player_id: PlayerId,
+) {
+    if store_character(game, accounts, account, player_id) {
+        save_accounts(accounts);
+    }
+}
+
+/// Writes every character in play into their account, then the accounts into the file. Called
+/// along with the snapshot so that the two never disagree on who carries what.
+pub fn save_online_characters(game: &Game, accounts: &mut AccountStore) {
+    let online: Vec<(PlayerId, String)> = game
+        .get_players()
+        .filter_map(|player| {
+            let player = player.borrow();
+            Some((player.get_id(), player.get_account()?.to_string()))
+        })
+        .collect();
+    if online.is_empty() {
+        return;
+    }
+
+    for (player_id, account) in &online {
+        store_character(game, accounts, account, *player_id);
+    }
+    save_accounts(accounts);
+}
+
+/// Puts the character of the player into the account, without writing the file. Returns whether
+/// there was a character to put.
+fn store_character(
+    game: &Game,
+    accounts: &mut AccountStore,
+    account: &str,
+    player_id: PlayerId,
+) -> bool {
+    let Some(char) = game.get_player_char(player_id) else {
+        return false;
+    };
+    let Some(room) = char.borrow().get_current_room().clone() else {
+        return false;
+    };
+
+    let world = room.get_world().borrow();
+    let char_id: CharacterId = char.borrow().get_id();
+    let Some(snapshot) = capture_character(&world, char_id, Some(room.get_id().into())) else {
+        return false;
+    };
+    let saved = SavedCharacter {
+        name: char.borrow().get_name(),
+        world: world.get_id().into(),
+        character: snapshot,
+    };
+
+    let Some(account) = accounts.get_account_mut(account) else {
+        return false;
+    };
+    account.save_character(saved);
+
+    true
+}
+
+fn save_accounts(accounts: &AccountStore) {
+    if let Err(error) = accounts.save() {
+        eprintln!("Cannot save the accounts: {error}");
+    }
+}
+
+fn enter_game(game: &mut Game, player_id: PlayerId) {
+    if let Err(error) = Look::new(None).execute(game, player_id) {
+        eprintln!(">>> {:?} failed a command: {error}", player_id);
+    }
+}
+
+fn send_character_menu(
+    game: &mut Game,
+    accounts: &AccountStore,
+    player_id: PlayerId,
+    account: &str,
+) {
+    let Some(account) = accounts.get_account(account) else {
+        return;
+    };
+
+    let mut text = String::new();
+    if account.get_characters().is_empty() {
+        text.push_str("You have no characters yet.\n");
+    } else {
+        text.push_str("Your characters:\n");
+        for (i, saved) in account.get_characters().iter().enumerate() {
+            text.push_str(&format!("  {}. {}\n", i + 1, saved.name));
+        }
+    }
+    text.push_str("Type the number or the name of a character to play, or 'new' to create one.");
+
+    game.send_to_player(player_id, &text);
+}
+
+pub(crate) fn send_name_rules(game: &mut Game, player_id: PlayerId) {
+    let text = format!(
+        "A name is one word of {} to {} letters. Try again:",
+        MIN_NAME_LENGTH, MAX_NAME_LENGTH
+    );
+    game.send_to_player(player_id, &text);
+}
+
+/// Capitalizes a valid name, like `alice` to `Alice`.
+pub(crate) fn normalize_name(input: &str) -> Option<String> {
+    let length = input.chars().count();
+    if !(MIN_NAME_LENGTH..=MAX_NAME_LENGTH).contains(&length)
+        || !input.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+
+    let lower = input.to_lowercase();
+    let mut chars = lower.chars();
+    let first = chars.next()?.to_ascii_uppercase();
+
+    Some(first.to_string() + chars.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::World;
+    use crate::creation::CreationRules;
+    use crate::game::Player;
+
+    fn rules() -> Rc<CreationRules> {
+        Rc::new(
+            ron::from_str(r#"(races: [(name: "Human")], classes: [(name: "Warrior")])"#).unwrap(),
+        )
+    }
+
+    fn setup(name: &str) -> (Game, AccountStore, PlayerId) {
+        let mut game = Game::new();
+        game.add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let player_id = game.add_player(Player::new(PlayerId::from(1))).unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "mud-jam-solo-accounts-{}-{}.ron",
+            name,
+            std::process::id()
+        ));
+        (game, AccountStore::new(path), player_id)
+    }
+
+    #[test]
+    fn saved_character_without_a_room_to_enter_is_not_left_in_the_world() {
+        let (mut game, mut accounts, player_id) = setup("no-room");
+        let mut account = Account::new(String::from("Alice"), "secret");
+        account.save_character(SavedCharacter {
+            name: String::from("Hero"),
+            world: 1,
+            character: ron::from_str(r#"(id: 7, room: None, name: Some("Hero"))"#).unwrap(),
+        });
+        accounts.add_account(account).unwrap();
+
+        let mut login = Login::new(WorldId::from(1), rules());
+        login.state = LoginState::ChooseCharacter {
+            account: String::from("Alice"),
+        };
+        login.handle(&mut game, &mut accounts, &HashSet::new(), player_id, "1");
+
+        assert!(!login.is_playing());
+        let world = game.get_world(WorldId::from(1)).unwrap().borrow();
+        assert_eq!(world.get_characters().count(), 0);
+        assert!(game.get_player_char(player_id).is_none());
+    }
+
+    #[test]
+    fn password_is_checked_against_the_salted_hash() {
+        let account = Account::new(String::from("Alice"), "secret");
+
+        assert!(account.check_password("secret"));
+        assert!(!account.check_password("Secret"));
+        assert!(!account.check_password(""));
+        assert_ne!(account.password_hash, "secret");
+    }
+
+    #[test]
+    fn wrong_passwords_are_refused_and_end_the_session_at_last() {
+        let (mut game, mut accounts, player_id) = setup("wrong-password");
+        accounts
+            .add_account(Account::new(String::from("Alice"), "secret"))
+            .unwrap();
+        let online = HashSet::new();
+
+        let mut login = Login::new(WorldId::from(1), rules());
+        login.handle(&mut game, &mut accounts, &online, player_id, "alice");
+        assert!(login.is_asking_password());
+
+        login.handle(&mut game, &mut accounts, &online, player_id, "guess");
+        assert!(login.is_asking_password());
+        assert_eq!(login.get_account(), None);
+        assert!(
+            game.take_output(player_id)
+                .unwrap()
+                .contains("Wrong password.")
+        );
+
+        login.handle(&mut game, &mut accounts, &online, player_id, "guess");
+        login.handle(&mut game, &mut accounts, &online, player_id, "guess");
+        assert!(!login.is_asking_password());
+        assert_eq!(game.take_ended_sessions(), [player_id]);
+    }
+
+    #[test]
+    fn account_in_use_by_another_connection_cannot_log_in() {
+        let (mut game, mut accounts, player_id) = setup("duplicate-login");
+        accounts
+            .add_account(Account::new(String::from("Alice"), "secret"))
+            .unwrap();
+        let online = HashSet::from([String::from("alice")]);
+
+        let mut login = Login::new(WorldId::from(1), rules());
+        login.handle(&mut game, &mut accounts, &online, player_id, "Alice");
+        login.handle(&mut game, &mut accounts, &online, player_id, "secret");
+
+        assert_eq!(login.get_account(), None);
+        assert!(!login.is_asking_password());
+        let output = game.take_output(player_id).unwrap();
+        assert!(output.contains("Someone is playing on that account already."));
+    }
+
+    #[test]
+    fn new_account_is_registered_and_goes_on_to_the_characters() {
+        let (mut game, mut accounts, player_id) = setup("new-account");
+        let online = HashSet::new();
+
+        let mut login = Login::new(WorldId::from(1), rules());
+        for input in [
+            "bob", "yes", "short", "secret1", "secret2", "secret1", "secret1",
+        ] {
+            login.handle(&mut game, &mut accounts, &online, player_id, input);
+        }
+
+        assert_eq!(login.get_account(), Some("Bob"));
+        assert!(!login.is_playing());
+        assert!(
+            accounts
+                .get_account("BOB")
+                .unwrap()
+                .check_password("secret1")
+        );
+        let output = game.take_output(player_id).unwrap();
+        assert!(output.contains("The passwords differ."));
+        assert!(output.contains("You have no characters yet."));
+
+        let _ = fs::remove_file(&accounts.path);
+    }
+}
@@ -1,16 +1,25 @@
-use crate::area::{MoveDirection, Room, RoomExit, WorldRef};
-use crate::game::{Game, PlayerId};
-use crate::socium::CharacterRef;
-use std::io;
-use std::ops::Deref;
+use crate::area::{DoorState, ExitKey, Room, Teleportable, World, WorldRef};
+use crate::combat::{resolve_attack, resolve_decay, resolve_engage, resolve_flee};
+use crate::error::GameError;
+use crate::game::{Game, GameEvent, PlayerId};
+use crate::item::{ItemFlag, ItemId, ItemLocation, ItemRef, WearSlot, capitalize};
+use crate::socium::{Character, CharacterId, CharacterRef, MAX_CARRY_WEIGHT, Stat, Vital};
+use crate::target::{
+    Target, find_character, find_character_in_world, find_item, find_item_in_world, find_items,
+};
 use std::rc::Rc;
 
+/// The movement points a step to the next room costs.
+pub(crate) const WALK_MOVEMENT_COST: i32 = 1;
+
 // ----------------------------------------------------------------------------------------------------
-// Commands via traits
+// Character actions
 // ----------------------------------------------------------------------------------------------------
 
 pub trait CharAction {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId);
+    /// Performs the command for the player. Refusals are told to the player, an error means the
+    /// game state is not what the command expected.
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError>;
 }
 
 #[derive(Debug)]
@@ -23,256 +32,1344 @@ pub struct Empty {}
 pub struct Quit {}
 
 #[derive(Debug)]
-pub struct MoveNorth {}
+pub struct Move {
+    exit: ExitKey,
+}
+
+#[derive(Debug)]
+pub struct Look {
+    target: Option<Target>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorOperation {
+    Open,
+    Close,
+    Lock,
+    Unlock,
+}
+
+#[derive(Debug)]
+pub struct OperateDoor {
+    operation: DoorOperation,
+    target: String,
+}
+
+#[derive(Debug)]
+pub struct GetItem {
+    target: Target,
+    container: Option<Target>,
+}
+
+#[derive(Debug)]
+pub struct DropItem {
+    target: Target,
+}
+
+#[derive(Debug)]
+pub struct PutItem {
+    target: Target,
+    container: Target,
+}
+
+#[derive(Debug)]
+pub struct Inventory {}
+
+#[derive(Debug)]
+pub struct Examine {
+    target: Target,
+}
 
 #[derive(Debug)]
-pub struct MoveSouth {}
+pub struct Wear {
+    target: Target,
+}
 
 #[derive(Debug)]
-pub struct MoveWest {}
+pub struct Wield {
+    target: Target,
+}
 
 #[derive(Debug)]
-pub struct MoveEast {}
+pub struct Remove {
+    target: Target,
+}
 
 #[derive(Debug)]
-pub struct MoveUp {}
+pub struct Equipment {}
 
 #[derive(Debug)]
-pub struct MoveDown {}
+pub struct Where {
+    target: Target,
+}
+
+#[derive(Debug)]
+pub struct Score {}
 
 #[derive(Debug)]
 pub struct Say {
-    params: Vec<String>,
+    text: String,
+}
+
+impl Say {
+    pub fn new(text: String) -> Self {
+        Self { text }
+    }
 }
 
 impl CharAction for UnknownCommand {
-    fn execute(&self, _game: &mut Game, _subject_id: PlayerId) {
-        println!("Unknown command")
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        game.send_to_player(subject_id, "Unknown command");
+
+        Ok(())
     }
 }
 
 impl CharAction for Empty {
-    fn execute(&self, _game: &mut Game, _subject_id: PlayerId) {}
+    fn execute(&self, _game: &mut Game, _subject_id: PlayerId) -> Result<(), GameError> {
+        Ok(())
+    }
 }
 
 impl CharAction for Quit {
-    fn execute(&self, _game: &mut Game, _subject_id: PlayerId) {
-        panic!("You decided to quit")
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        game.send_to_player(subject_id, "Goodbye!");
+        game.end_session(subject_id);
+
+        Ok(())
     }
 }
 
-impl CharAction for MoveNorth {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::North);
+impl Move {
+    pub fn new(exit: impl Into<ExitKey>) -> Self {
+        Self { exit: exit.into() }
     }
 }
 
-impl CharAction for MoveSouth {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::South);
+impl CharAction for Move {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        move_through_exit(game, subject_id, &self.exit)
     }
 }
 
-impl CharAction for MoveWest {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::West);
+impl OperateDoor {
+    pub fn new(operation: DoorOperation, target: String) -> Self {
+        Self { operation, target }
     }
 }
 
-impl CharAction for MoveEast {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::East);
+impl CharAction for OperateDoor {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        operate_door(game, subject_id, self.operation, &self.target)
     }
 }
 
-impl CharAction for MoveUp {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::Up);
+impl GetItem {
+    pub fn new(target: Target, container: Option<Target>) -> Self {
+        Self { target, container }
     }
 }
 
-impl CharAction for MoveDown {
-    fn execute(&self, game: &mut Game, subject_id: PlayerId) {
-        move_to_direction(game, subject_id, MoveDirection::Down);
+impl CharAction for GetItem {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let world = room.get_world().clone();
+
+        let container = match &self.container {
+            None => None,
+            Some(word) => {
+                let found = {
+                    let world = world.borrow();
+                    let nearby = [get_belongings(&char.borrow()), room.get_items()].concat();
+                    find_item(&world, &nearby, word)
+                };
+                let Some(container) = found else {
+                    game.send_to_player(subject_id, &format!("You see no {} here.", word));
+                    return Ok(());
+                };
+                if !container.borrow().has_flag(ItemFlag::Container) {
+                    let text = format!("{} is not a container.", capitalize(&short_of(&container)));
+                    game.send_to_player(subject_id, &text);
+                    return Ok(());
+                }
+                Some(container)
+            }
+        };
+
+        let items = {
+            let world = world.borrow();
+            match &container {
+                None => find_items(&world, &room.get_items(), &self.target),
+                Some(c) => find_items(&world, c.borrow().get_contents(), &self.target),
+            }
+        };
+        if items.is_empty() {
+            let text = match &container {
+                None => format!("You see no {} here.", self.target),
+                Some(c) => format!("There is no {} in {}.", self.target, short_of(c)),
+            };
+            game.send_to_player(subject_id, &text);
+            return Ok(());
+        }
+
+        for item in items {
+            take_item(game, subject_id, &char, &room, &item, container.as_ref())?;
+        }
+
+        Ok(())
     }
 }
 
+/// Moves the item from the room or the container to the inventory, unless it cannot be taken.
+fn take_item(
+    game: &mut Game,
+    subject_id: PlayerId,
+    char: &CharacterRef,
+    room: &Rc<Room>,
+    item: &ItemRef,
+    container: Option<&ItemRef>,
+) -> Result<(), GameError> {
+    let char_id = char.borrow().get_id();
+    let char_name = char.borrow().get_name();
+    let world = room.get_world().clone();
+    let item_id = item.borrow().get_id();
+    let item_name = short_of(item);
 
-impl CharAction for Say {
-    fn execute(&self, _game: &mut Game, _subject_id: PlayerId) {
-        println!("SAY: {:?}", self.params);
+    if item.borrow().has_flag(ItemFlag::NoTake) {
+        game.send_to_player(subject_id, &format!("You cannot take {}.", item_name));
+        return Ok(());
     }
-}
 
-fn move_to_direction(game: &mut Game, subject_id: PlayerId, direction: MoveDirection) {
-    let free_char;
+    // An item from a container the character carries, however deep, weighs on them already.
+    let carried_already = world.borrow().is_item_carried_by(item_id, char_id);
+    let new_weight =
+        world.borrow().get_carried_weight(char_id) + world.borrow().get_item_weight(item_id);
+    if !carried_already && new_weight > MAX_CARRY_WEIGHT {
+        game.send_to_player(
+            subject_id,
+            &format!("{} is too heavy for you.", capitalize(&item_name)),
+        );
+        return Ok(());
+    }
 
-    {
-        let player = game.get_player(subject_id).unwrap();
-        let player_ref = player.borrow();
-        let char = player_ref.get_main_char();
+    world
+        .borrow()
+        .move_item(item_id, ItemLocation::Character(char_id))?;
 
-        if char.is_none() {
-            println!("You have no physical body!");
-            return;
+    match container {
+        None => {
+            game.send_to_player(subject_id, &format!("You get {}.", item_name));
+            game.send_to_room(
+                room,
+                Some(subject_id),
+                &format!("{} gets {}.", char_name, item_name),
+            );
         }
+        Some(c) => {
+            let container_name = short_of(c);
+            game.send_to_player(
+                subject_id,
+                &format!("You get {} from {}.", item_name, container_name),
+            );
+            game.send_to_room(
+                room,
+                Some(subject_id),
+                &format!("{} gets {} from {}.", char_name, item_name, container_name),
+            );
+        }
+    }
 
-        let Some(char) = char else { unreachable!() };
-        free_char = char.clone();
+    game.publish(GameEvent::ItemTaken {
+        who: char.clone(),
+        room: room.clone(),
+        item: item_id,
+    });
+
+    Ok(())
+}
+
+impl DropItem {
+    pub fn new(target: Target) -> Self {
+        Self { target }
     }
+}
 
-    let char_ref = free_char.borrow();
+impl CharAction for DropItem {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let char_name = char.borrow().get_name();
+        let world = room.get_world().clone();
 
-    let current_room = char_ref.get_current_room();
+        let items = find_items(&world.borrow(), char.borrow().get_inventory(), &self.target);
+        if items.is_empty() {
+            let worn = get_worn(&char.borrow());
+            let text = match find_item(&world.borrow(), &worn, &self.target) {
+                Some(item) => format!("You have to remove {} first.", short_of(&item)),
+                None => format!("You carry no {}.", self.target),
+            };
+            game.send_to_player(subject_id, &text);
+            return Ok(());
+        }
 
-    if current_room.is_none() {
-        println!("You are nowhere and cannot step!");
-        return;
+        for item in items {
+            let item_id = item.borrow().get_id();
+            let item_name = short_of(&item);
+
+            world
+                .borrow()
+                .move_item(item_id, ItemLocation::Room(room.get_id()))?;
+
+            game.send_to_player(subject_id, &format!("You drop {}.", item_name));
+            game.send_to_room(
+                &room,
+                Some(subject_id),
+                &format!("{} drops {}.", char_name, item_name),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl PutItem {
+    pub fn new(target: Target, container: Target) -> Self {
+        Self { target, container }
     }
+}
+
+impl CharAction for PutItem {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let char_name = char.borrow().get_name();
+        let world = room.get_world().clone();
+
+        let inventory = char.borrow().get_inventory().to_vec();
+
+        let items = find_items(&world.borrow(), &inventory, &self.target);
+        if items.is_empty() {
+            game.send_to_player(subject_id, &format!("You carry no {}.", self.target));
+            return Ok(());
+        }
+
+        let nearby = [inventory, get_worn(&char.borrow()), room.get_items()].concat();
+        let Some(container) = find_item(&world.borrow(), &nearby, &self.container) else {
+            game.send_to_player(subject_id, &format!("You see no {} here.", self.container));
+            return Ok(());
+        };
+
+        let container_id = container.borrow().get_id();
+        let container_name = short_of(&container);
+
+        if !container.borrow().has_flag(ItemFlag::Container) {
+            let text = format!("{} is not a container.", capitalize(&container_name));
+            game.send_to_player(subject_id, &text);
+            return Ok(());
+        }
 
-    let Some(current_room) = current_room else { unreachable!() };
-
-    match current_room.get_exit(&direction).borrow().deref() {
-        RoomExit::DeadEnd => {
-            println!("You cannot go this way!");
-        },
-        RoomExit::Pathway(weak_to_room) => {
-            match weak_to_room.upgrade() {
-                None => println!("The game is failed to moving you :("),
-                Some(to_room) => {
-                    let world = current_room.get_world().clone();
-                    let world_ref = world.borrow();
-                    let char = world_ref.get_character(char_ref.get_id()).unwrap();
-
-                    println!("${:?} moving {:?}...", subject_id, direction);
-                    game.queue_action(GameAction::WalkFromTo {
-                        who: char.clone(),
-                        from: current_room.clone(),
-                        to: to_room.clone(),
-                    });
+        for item in items {
+            let item_id = item.borrow().get_id();
+            let item_name = short_of(&item);
+
+            if item_id == container_id || world.borrow().is_item_within(container_id, item_id) {
+                // Putting everything away leaves the container out without a word.
+                if !self.target.is_all() {
+                    let text = format!("You cannot put {} inside itself.", item_name);
+                    game.send_to_player(subject_id, &text);
                 }
+                continue;
             }
-        },
+
+            world
+                .borrow()
+                .move_item(item_id, ItemLocation::Container(container_id))?;
+
+            game.send_to_player(
+                subject_id,
+                &format!("You put {} in {}.", item_name, container_name),
+            );
+            game.send_to_room(
+                &room,
+                Some(subject_id),
+                &format!("{} puts {} in {}.", char_name, item_name, container_name),
+            );
+        }
+
+        Ok(())
     }
 }
 
-pub fn ask_command_as_action() -> Box<dyn CharAction> {
-    println!("Your action? ");
+impl CharAction for Inventory {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some(char) = game.get_player_char(subject_id) else {
+            game.send_to_player(subject_id, "You have no physical body!");
+            return Ok(());
+        };
 
-    let mut input = String::with_capacity(200);
-    // input.clear();
+        let text = {
+            let char = char.borrow();
+            let Some(room) = char.get_current_room() else {
+                game.send_to_player(subject_id, "You are nowhere and carry nothing.");
+                return Ok(());
+            };
+            let world = room.get_world().borrow();
 
-    match io::stdin().read_line(&mut input) {
-        Ok(n) => {
-            println!("> {n} bytes read: {input}");
-        }
-        Err(error) => println!("error: {error}"),
+            if char.get_inventory().is_empty() {
+                String::from("You are carrying nothing.")
+            } else {
+                let mut text = String::from("You are carrying:");
+                text.push_str(&render_item_list(&world, char.get_inventory()));
+                text
+            }
+        };
+
+        game.send_to_player(subject_id, &text);
+
+        Ok(())
     }
+}
 
-    command_to_character_action(input)
+impl Examine {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
 }
 
-fn command_to_character_action(input: String) -> Box<dyn CharAction> {
-    let mut words = input.split_whitespace();
-    let command = words.next().unwrap_or("").to_ascii_lowercase();
-    let params: Vec<String> = words.map(|m| m.to_string()).collect();
+impl CharAction for Examine {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let world = room.get_world().clone();
+        let world = world.borrow();
+
+        let nearby = [get_belongings(&char.borrow()), room.get_items()].concat();
+        let Some(item) = find_item(&world, &nearby, &self.target) else {
+            game.send_to_player(subject_id, &format!("You see no {} here.", self.target));
+            return Ok(());
+        };
 
-    println!(">>> Command: \"{}\" {:?}", command, params);
+        game.send_to_player(subject_id, &render_item(&world, &item));
 
-    // TODO Use clap library as the input parser to all available commands
-    // TODO Return Command instance: input + action instance
-    match command.as_str() {
-        "" => Box::new(Empty {}),
-        "quit" => Box::new(Quit {}),
-        "north" => Box::new(MoveNorth {}),
-        "south" => Box::new(MoveSouth {}),
-        "west" => Box::new(MoveWest {}),
-        "east" => Box::new(MoveEast {}),
-        "up" => Box::new(MoveUp {}),
-        "down" => Box::new(MoveDown {}),
-        "say" => Box::new(Say { params }),
-        _ => Box::new(UnknownCommand {}),
+        Ok(())
     }
 }
 
-// ----------------------------------------------------------------------------------------------------
-// Commands via enum
-// ----------------------------------------------------------------------------------------------------
+impl Where {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
 
-pub enum Command {
-    UnknownCommand,
-    Empty,
-    Quit,
-    MoveNorth,
-    MoveSouth,
-    MoveWest,
-    MoveEast,
-    MoveUp,
-    MoveDown,
-    Say(Vec<String>),
-}
-
-impl Command {
-    pub fn execute(&self, _w: &WorldRef, _subject_id: u32) {
-        print!("command: ");
-        match self {
-            Command::UnknownCommand => println!("u"),
-            Command::Empty => {}
-            Command::Quit => println!("q"),
-            Command::MoveNorth => println!("mn"),
-            Command::MoveSouth => println!("ms"),
-            Command::MoveWest => println!("mw"),
-            Command::MoveEast => println!("me"),
-            Command::MoveUp => println!("mu"),
-            Command::MoveDown => println!("md"),
-            Command::Say(params) => println!("s: {:?}", params),
+/// Tells the room of the first character, or else of the first item, the target picks in the
+/// whole world.
+impl CharAction for Where {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((_, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let world = room.get_world().clone();
+        let world = world.borrow();
+
+        let found = match find_character_in_world(&world, &self.target) {
+            Some(char) => {
+                let char = char.borrow();
+                Some((char.get_name(), char.get_current_room().clone()))
+            }
+            None => find_item_in_world(&world, &self.target).map(|item| {
+                let item_id = item.borrow().get_id();
+                (short_of(&item), world.get_item_room(item_id))
+            }),
+        };
+
+        let text = match found {
+            Some((name, Some(room))) => {
+                format!("{} is in {}.", capitalize(&name), room.get_title())
+            }
+            _ => format!("You cannot find any {} in this world.", self.target),
+        };
+        game.send_to_player(subject_id, &text);
+
+        Ok(())
+    }
+}
+
+impl Wear {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
+
+impl CharAction for Wear {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        equip_item(game, subject_id, &self.target, false)
+    }
+}
+
+impl Wield {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
+
+impl CharAction for Wield {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        equip_item(game, subject_id, &self.target, true)
+    }
+}
+
+impl Remove {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
+
+impl CharAction for Remove {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let char_id = char.borrow().get_id();
+        let char_name = char.borrow().get_name();
+        let world = room.get_world().clone();
+
+        let worn = get_worn(&char.borrow());
+        let Some(item) = find_item(&world.borrow(), &worn, &self.target) else {
+            game.send_to_player(
+                subject_id,
+                &format!("You are not using any {}.", self.target),
+            );
+            return Ok(());
+        };
+
+        let item_id = item.borrow().get_id();
+        let item_name = short_of(&item);
+
+        world
+            .borrow()
+            .move_item(item_id, ItemLocation::Character(char_id))?;
+
+        game.send_to_player(subject_id, &format!("You stop using {}.", item_name));
+        game.send_to_room(
+            &room,
+            Some(subject_id),
+            &format!("{} stops using {}.", char_name, item_name),
+        );
+
+        Ok(())
+    }
+}
+
+impl CharAction for Equipment {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let world = room.get_world().borrow();
+
+        let text = if char.borrow().get_equipment().is_empty() {
+            String::from("You are using nothing.")
+        } else {
+            let mut text = String::from("You are using:");
+            text.push_str(&render_equipment(&world, &char.borrow()));
+            text
+        };
+
+        game.send_to_player(subject_id, &text);
+
+        Ok(())
+    }
+}
+
+impl Look {
+    /// Looks around the room, or at the character or item named by the target.
+    pub fn new(target: Option<Target>) -> Self {
+        Self { target }
+    }
+}
+
+impl CharAction for Look {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        if let Some(target) = &self.target {
+            return look_at(game, subject_id, target);
         }
+
+        let Some(char) = game.get_player_char(subject_id) else {
+            game.send_to_player(subject_id, "You have no physical body!");
+            return Ok(());
+        };
+
+        let char_id = char.borrow().get_id();
+        let room = char.borrow().get_current_room().clone();
+
+        match room {
+            None => game.send_to_player(subject_id, "You are nowhere. There is nothing to see."),
+            Some(room) => game.send_to_player(subject_id, &render_room(&room, char_id, false)),
+        }
+
+        Ok(())
+    }
+}
+
+impl CharAction for Score {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+        let world = room.get_world().borrow();
+        let char = char.borrow();
+        let stats = world.get_character_stats(char.get_id());
+
+        let mut text = match (char.get_race(), char.get_class()) {
+            (Some(race), Some(class)) => {
+                format!("You are {} the {} {}.", char.get_name(), race, class)
+            }
+            _ => format!("You are {}.", char.get_name()),
+        };
+        if let Some(opponent) = char.get_fighting().and_then(|id| world.get_character(id)) {
+            text.push_str(&format!(
+                " You are fighting {}.",
+                opponent.borrow().get_name()
+            ));
+        }
+        for vital in Vital::ALL {
+            let pool = char.get_vital(vital);
+            let name = capitalize(vital.get_name());
+            text.push_str(&format!(
+                "\n  {:<14}{}/{}",
+                name,
+                pool.get_current(),
+                pool.get_max()
+            ));
+        }
+        for stat in Stat::ALL {
+            let name = capitalize(stat.get_name());
+            text.push_str(&format!("\n  {:<14}{}", name, stats.get(stat)));
+        }
+        text.push_str(&format!(
+            "\n  {:<14}{}/{}",
+            "Carrying",
+            world.get_carried_weight(char.get_id()),
+            MAX_CARRY_WEIGHT
+        ));
+
+        game.send_to_player(subject_id, &text);
+
+        Ok(())
     }
 }
 
-pub fn ask_command_as_enum() -> Command {
-    println!("Your action? ");
+impl CharAction for Say {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        if self.text.is_empty() {
+            game.send_to_player(subject_id, "Say what?");
+            return Ok(());
+        }
+
+        let Some(char) = game.get_player_char(subject_id) else {
+            game.send_to_player(subject_id, "You have no physical body!");
+            return Ok(());
+        };
 
-    let mut input = String::with_capacity(200);
-    // input.clear();
+        game.queue_action(GameAction::Say {
+            who: char,
+            text: self.text.clone(),
+        });
 
-    match io::stdin().read_line(&mut input) {
-        Ok(n) => {
-            println!("> {n} bytes read: {input}");
+        Ok(())
+    }
+}
+
+fn look_at(game: &mut Game, subject_id: PlayerId, target: &Target) -> Result<(), GameError> {
+    let Some((char, room)) = locate_subject(game, subject_id) else {
+        return Ok(());
+    };
+    let world = room.get_world().clone();
+    let world = world.borrow();
+
+    if let Some(other) = find_character(&world, &room, target) {
+        let other = other.borrow();
+        let mut text = format!("You look at {}.", other.get_name());
+        if !other.get_description().is_empty() {
+            text.push('\n');
+            text.push_str(other.get_description());
+        }
+        if other.get_equipment().is_empty() {
+            text.push_str("\nThey are using nothing.");
+        } else {
+            text.push_str("\nThey are using:");
+            text.push_str(&render_equipment(&world, &other));
         }
-        Err(error) => println!("error: {error}"),
+        game.send_to_player(subject_id, &text);
+        return Ok(());
     }
 
-    command_to_enum(input)
+    let nearby = [get_belongings(&char.borrow()), room.get_items()].concat();
+    match find_item(&world, &nearby, target) {
+        Some(item) => game.send_to_player(subject_id, &render_item(&world, &item)),
+        None => game.send_to_player(subject_id, &format!("You see no {} here.", target)),
+    }
+
+    Ok(())
 }
 
-fn command_to_enum(input: String) -> Command {
-    let mut words = input.split_whitespace();
-    let command = words.next().unwrap_or("").to_ascii_lowercase();
-    let params: Vec<String> = words.map(|m| m.to_string()).collect();
+/// Wears the item from the inventory on the first free slot which fits it. Wielding takes the
+/// hands, wearing the rest of the body.
+fn equip_item(
+    game: &mut Game,
+    subject_id: PlayerId,
+    target: &Target,
+    wield: bool,
+) -> Result<(), GameError> {
+    let Some((char, room)) = locate_subject(game, subject_id) else {
+        return Ok(());
+    };
+    let char_id = char.borrow().get_id();
+    let char_name = char.borrow().get_name();
+    let world = room.get_world().clone();
+
+    let found = find_item(&world.borrow(), char.borrow().get_inventory(), target);
+    let Some(item) = found else {
+        game.send_to_player(subject_id, &format!("You carry no {}.", target));
+        return Ok(());
+    };
+
+    let item_id = item.borrow().get_id();
+    let item_name = short_of(&item);
+    let slots: Vec<WearSlot> = item.borrow().get_slots().to_vec();
+    let fitting: Vec<WearSlot> = slots
+        .iter()
+        .copied()
+        .filter(|s| s.is_hand() == wield)
+        .collect();
+
+    let Some(first) = fitting.first() else {
+        let text = match (wield, slots.is_empty()) {
+            (true, _) => format!("You cannot wield {}.", item_name),
+            (false, false) => format!("You cannot wear {}. Try to wield it.", item_name),
+            (false, true) => format!("You cannot wear {}.", item_name),
+        };
+        game.send_to_player(subject_id, &text);
+        return Ok(());
+    };
+
+    let free = fitting
+        .iter()
+        .copied()
+        .find(|slot| char.borrow().get_equipped(*slot).is_none());
+    let Some(slot) = free else {
+        let text = if wield {
+            String::from("Your hands are full.")
+        } else {
+            format!("You already wear something on your {}.", first.get_name())
+        };
+        game.send_to_player(subject_id, &text);
+        return Ok(());
+    };
+
+    world
+        .borrow()
+        .move_item(item_id, ItemLocation::Equipped(char_id, slot))?;
+
+    let (own, other) = if wield {
+        (
+            format!("You wield {} in your {}.", item_name, slot.get_name()),
+            format!("{} wields {}.", char_name, item_name),
+        )
+    } else {
+        (
+            format!("You wear {} on your {}.", item_name, slot.get_name()),
+            format!("{} wears {}.", char_name, item_name),
+        )
+    };
+    game.send_to_player(subject_id, &own);
+    game.send_to_room(&room, Some(subject_id), &other);
+
+    Ok(())
+}
+
+fn move_through_exit(
+    game: &mut Game,
+    subject_id: PlayerId,
+    exit: &ExitKey,
+) -> Result<(), GameError> {
+    let free_char;
+
+    {
+        let player = game.require_player(subject_id)?;
+        let player_ref = player.borrow();
+        free_char = player_ref.get_main_char().clone();
+    }
+
+    let Some(free_char) = free_char else {
+        game.send_to_player(subject_id, "You have no physical body!");
+        return Ok(());
+    };
 
-    println!(">>> Command: \"{}\" {:?}", command, params);
+    let current_room = free_char.borrow().get_current_room().clone();
 
-    // TODO Use clap library as the input parser to all available commands
-    // TODO Return Command instance: input + action instance
-    match command.as_str() {
-        "" => Command::Empty,
-        "quit" => Command::Quit,
-        "north" => Command::MoveNorth,
-        "south" => Command::MoveSouth,
-        "west" => Command::MoveWest,
-        "east" => Command::MoveEast,
-        "up" => Command::MoveUp,
-        "down" => Command::MoveDown,
-        "say" => Command::Say(params),
-        _ => Command::UnknownCommand,
+    let Some(current_room) = current_room else {
+        game.send_to_player(subject_id, "You are nowhere and cannot step!");
+        return Ok(());
+    };
+
+    if free_char.borrow().is_fighting() {
+        game.send_to_player(subject_id, "You are fighting! Try to flee.");
+        return Ok(());
     }
+
+    match current_room.get_exit_target(exit)? {
+        None => {
+            game.send_to_player(subject_id, "You cannot go this way!");
+        }
+        Some(to_room) => {
+            if let Some(door) = current_room.get_door(exit)
+                && !door.borrow().is_open()
+            {
+                let door = door.borrow();
+                if door.is_secret() {
+                    game.send_to_player(subject_id, "You cannot go this way!");
+                } else {
+                    game.send_to_player(subject_id, &format!("The {} is closed.", door.get_name()));
+                }
+                return Ok(());
+            }
+
+            if free_char.borrow().get_vital(Vital::Movement).get_current() < WALK_MOVEMENT_COST {
+                game.send_to_player(subject_id, "You are too exhausted to walk.");
+                return Ok(());
+            }
+
+            game.queue_action(GameAction::WalkFromTo {
+                who: free_char,
+                from: current_room,
+                to: to_room,
+                exit: exit.clone(),
+            });
+        }
+    }
+
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------------------------------
 // Actions
 // ----------------------------------------------------------------------------------------------------
 
+#[derive(Clone)]
 pub enum GameAction {
-    WalkFromTo { who: CharacterRef, from: Rc<Room>, to: Rc<Room> },
+    WalkFromTo {
+        who: CharacterRef,
+        from: Rc<Room>,
+        to: Rc<Room>,
+        exit: ExitKey,
+    },
+    Engage {
+        attacker: CharacterRef,
+        victim: CharacterRef,
+    },
+    Attack {
+        attacker: CharacterRef,
+        victim: CharacterRef,
+    },
+    Say {
+        who: CharacterRef,
+        text: String,
+    },
+    /// Walks away from a fight, which only ends once the character gets through.
+    Flee {
+        who: CharacterRef,
+        from: Rc<Room>,
+        to: Rc<Room>,
+        exit: ExitKey,
+    },
+    /// Takes the corpse out of the world and leaves its contents where it lay.
+    Decay {
+        world: WorldRef,
+        corpse: ItemId,
+    },
+}
+
+impl GameAction {
+    /// Applies the action to the game state. The world could change between queueing and
+    /// resolving, so every precondition is checked again here.
+    pub fn resolve(self, game: &mut Game) {
+        match self {
+            GameAction::WalkFromTo {
+                who,
+                from,
+                to,
+                exit,
+            } => resolve_walk(game, who, from, to, exit),
+            GameAction::Engage { attacker, victim } => resolve_engage(game, attacker, victim),
+            GameAction::Attack { attacker, victim } => resolve_attack(game, attacker, victim),
+            GameAction::Say { who, text } => resolve_say(game, who, text),
+            GameAction::Flee {
+                who,
+                from,
+                to,
+                exit,
+            } => resolve_flee(game, who, from, to, exit),
+            GameAction::Decay { world, corpse } => resolve_decay(game, world, corpse),
+        }
+    }
+}
+
+pub(crate) fn resolve_walk(
+    game: &mut Game,
+    who: CharacterRef,
+    from: Rc<Room>,
+    to: Rc<Room>,
+    exit: ExitKey,
+) {
+    let (owner_id, char_name) = {
+        let char_ref = who.borrow();
+        let owner_id = char_ref.get_owner().as_ref().map(|p| p.borrow().get_id());
+
+        (owner_id, char_ref.get_name())
+    };
+
+    let exit_leads_to = match from.get_exit_target(&exit) {
+        Ok(Some(r)) => Rc::ptr_eq(&r, &to),
+        _ => false,
+    };
+
+    if !exit_leads_to {
+        let text = format!("The way {} is not there anymore.", describe_way(&exit));
+        reject(game, who, owner_id, &text);
+        return;
+    }
+
+    if let Some(door) = from.get_door(&exit)
+        && !door.borrow().is_open()
+    {
+        let text = format!("The {} has closed before you.", door.borrow().get_name());
+        reject(game, who, owner_id, &text);
+        return;
+    }
+
+    if who.borrow().get_vital(Vital::Movement).get_current() < WALK_MOVEMENT_COST {
+        reject(game, who, owner_id, "You are too exhausted to walk.");
+        return;
+    }
+
+    if !who.borrow_mut().move_from_to(&from, &to) {
+        let text = "You are not where you were going from anymore.";
+        reject(game, who, owner_id, text);
+        return;
+    }
+
+    who.borrow_mut()
+        .get_vital_mut(Vital::Movement)
+        .spend(WALK_MOVEMENT_COST);
+
+    game.send_to_room(
+        &from,
+        owner_id,
+        &format!("{} leaves {}.", char_name, describe_way(&exit)),
+    );
+    game.send_to_room(&to, owner_id, &format!("{} arrives.", char_name));
+
+    if let Some(owner_id) = owner_id {
+        let char_id = who.borrow().get_id();
+
+        game.send_to_player(owner_id, &format!("You walk {}.", describe_way(&exit)));
+        game.send_to_player(owner_id, &render_room(&to, char_id, true));
+    }
+
+    game.publish(GameEvent::CharacterMoved { who, from, to });
+}
+
+/// Tells the player, if any, why the action did not happen and lets the subscribers know.
+fn reject(game: &mut Game, who: CharacterRef, owner_id: Option<PlayerId>, reason: &str) {
+    if let Some(owner_id) = owner_id {
+        game.send_to_player(owner_id, reason);
+    }
+
+    game.publish(GameEvent::ActionRejected {
+        who,
+        reason: reason.to_string(),
+    });
+}
+
+fn resolve_say(game: &mut Game, who: CharacterRef, text: String) {
+    let (owner_id, char_name, room) = {
+        let char_ref = who.borrow();
+        let owner_id = char_ref.get_owner().as_ref().map(|p| p.borrow().get_id());
+
+        (
+            owner_id,
+            char_ref.get_name(),
+            char_ref.get_current_room().clone(),
+        )
+    };
+
+    if let Some(owner_id) = owner_id {
+        game.send_to_player(owner_id, &format!("You say '{}'", text));
+    }
+
+    if let Some(room) = room {
+        game.send_to_room(&room, owner_id, &format!("{} says '{}'", char_name, text));
+
+        game.publish(GameEvent::SaidInRoom { who, room, text });
+    }
+}
+
+fn operate_door(
+    game: &mut Game,
+    subject_id: PlayerId,
+    operation: DoorOperation,
+    target: &str,
+) -> Result<(), GameError> {
+    let Some(char) = game.get_player_char(subject_id) else {
+        game.send_to_player(subject_id, "You have no physical body!");
+        return Ok(());
+    };
+
+    let char_name = char.borrow().get_name();
+    let Some(room) = char.borrow().get_current_room().clone() else {
+        game.send_to_player(subject_id, "You are nowhere. There is nothing to touch.");
+        return Ok(());
+    };
+
+    // The exit names any door on it, the door name only the doors which are not secret.
+    let by_exit = ExitKey::from_name(target);
+    let exit = match room.get_door(&by_exit) {
+        Some(_) => Some(by_exit),
+        None => room.find_door(target),
+    };
+    let Some((exit, door)) = exit.and_then(|e| room.get_door(&e).map(|door| (e, door))) else {
+        game.send_to_player(subject_id, &format!("You see no {} here.", target));
+        return Ok(());
+    };
+
+    let door_name = door.borrow().get_name().to_string();
+    let state = door.borrow().get_state();
+
+    let (new_state, verb) = match (operation, state) {
+        (DoorOperation::Open, DoorState::Closed) => (DoorState::Open, "open"),
+        (DoorOperation::Open, DoorState::Open) => {
+            game.send_to_player(subject_id, &format!("The {} is already open.", door_name));
+            return Ok(());
+        }
+        (DoorOperation::Open, DoorState::Locked) => {
+            game.send_to_player(subject_id, &format!("The {} is locked.", door_name));
+            return Ok(());
+        }
+        (DoorOperation::Close, DoorState::Open) => (DoorState::Closed, "close"),
+        (DoorOperation::Close, _) => {
+            game.send_to_player(subject_id, &format!("The {} is already closed.", door_name));
+            return Ok(());
+        }
+        (DoorOperation::Lock, DoorState::Closed) => (DoorState::Locked, "lock"),
+        (DoorOperation::Lock, DoorState::Open) => {
+            game.send_to_player(subject_id, &format!("Close the {} first.", door_name));
+            return Ok(());
+        }
+        (DoorOperation::Lock, DoorState::Locked) => {
+            game.send_to_player(subject_id, &format!("The {} is already locked.", door_name));
+            return Ok(());
+        }
+        (DoorOperation::Unlock, DoorState::Locked) => (DoorState::Closed, "unlock"),
+        (DoorOperation::Unlock, _) => {
+            game.send_to_player(subject_id, &format!("The {} is not locked.", door_name));
+            return Ok(());
+        }
+    };
+
+    // The key works from the inventory, the equipment or any bag the character carries.
+    let needs_key = matches!(operation, DoorOperation::Lock | DoorOperation::Unlock);
+    if needs_key
+        && let Some(key) = door.borrow().get_key()
+        && !room
+            .get_world()
+            .borrow()
+            .is_item_carried_by(key, char.borrow().get_id())
+    {
+        game.send_to_player(subject_id, "You lack the key.");
+        return Ok(());
+    }
+
+    door.borrow_mut().set_state(new_state);
+
+    game.send_to_player(subject_id, &format!("You {} the {}.", verb, door_name));
+    game.send_to_room(
+        &room,
+        Some(subject_id),
+        &format!("{} {}s the {}.", char_name, verb, door_name),
+    );
+
+    if let Ok(Some(to)) = room.get_exit_target(&exit)
+        && !Rc::ptr_eq(&to, &room)
+    {
+        let participle = match operation {
+            DoorOperation::Open => "opened",
+            DoorOperation::Close => "closed",
+            DoorOperation::Lock => "locked",
+            DoorOperation::Unlock => "unlocked",
+        };
+        let text = format!("The {} is {} from the other side.", door_name, participle);
+        game.send_to_room(&to, None, &text);
+    }
+
+    Ok(())
+}
+
+/// Finds the character of the player and the room they stand in. Tells the player when either
+/// is missing.
+pub(crate) fn locate_subject(
+    game: &mut Game,
+    subject_id: PlayerId,
+) -> Option<(CharacterRef, Rc<Room>)> {
+    let Some(char) = game.get_player_char(subject_id) else {
+        game.send_to_player(subject_id, "You have no physical body!");
+        return None;
+    };
+
+    let room = char.borrow().get_current_room().clone();
+    let Some(room) = room else {
+        game.send_to_player(subject_id, "You are nowhere. There is nothing around.");
+        return None;
+    };
+
+    Some((char, room))
+}
+
+/// The items the character wears or wields, in the order of the slots.
+fn get_worn(char: &Character) -> Vec<ItemId> {
+    char.get_equipment().values().copied().collect()
+}
+
+/// The items the character carries or wears.
+fn get_belongings(char: &Character) -> Vec<ItemId> {
+    [char.get_inventory().to_vec(), get_worn(char)].concat()
+}
+
+fn short_of(item: &ItemRef) -> String {
+    item.borrow().get_short_description().to_string()
+}
+
+/// Lists the items one per line, each line starting with a line break.
+fn render_item_list(world: &World, item_ids: &[ItemId]) -> String {
+    item_ids
+        .iter()
+        .filter_map(|id| world.get_item(*id))
+        .map(|item| format!("\n  {}", item.borrow().get_short_description()))
+        .collect()
+}
+
+/// Lists the worn items one per line with their slots, each line starting with a line break.
+fn render_equipment(world: &World, char: &Character) -> String {
+    char.get_equipment()
+        .iter()
+        .filter_map(|(slot, id)| Some((slot, world.get_item(*id)?)))
+        .map(|(slot, item)| {
+            let label = format!("<{}>", slot.get_name());
+            format!("\n  {:<12}{}", label, item.borrow().get_short_description())
+        })
+        .collect()
+}
+
+/// The text shown when the item is examined.
+fn render_item(world: &World, item: &ItemRef) -> String {
+    let item = item.borrow();
+    let mut text = if item.get_description().is_empty() {
+        format!(
+            "You see nothing special about {}.",
+            item.get_short_description()
+        )
+    } else {
+        item.get_description().to_string()
+    };
+
+    if item.has_flag(ItemFlag::Container) {
+        if item.get_contents().is_empty() {
+            text.push_str("\nIt is empty.");
+        } else {
+            text.push_str("\nIt contains:");
+            text.push_str(&render_item_list(world, item.get_contents()));
+        }
+    }
+
+    text
+}
+
+/// Words for the way a character goes, like `north` or `through 'enter portal'`.
+pub(crate) fn describe_way(exit: &ExitKey) -> String {
+    match exit {
+        ExitKey::Direction(direction) => direction.get_name().to_string(),
+        ExitKey::Keyword(keyword) => format!("through '{}'", keyword),
+    }
+}
+
+/// Describes the room as the character sees it. The brief form prefers the short description.
+pub(crate) fn render_room(room: &Room, viewer: CharacterId, brief: bool) -> String {
+    let mut text = String::from(room.get_title());
+
+    let description = match room.get_short_description() {
+        Some(short) if brief => short.as_str(),
+        _ => room.get_description(),
+    };
+    if !description.is_empty() {
+        text.push('\n');
+        text.push_str(description);
+    }
+
+    let exits: Vec<String> = room
+        .get_pathways()
+        .iter()
+        .filter_map(|(exit, _)| {
+            let name = match exit {
+                ExitKey::Keyword(keyword) if keyword.contains(' ') => format!("'{}'", keyword),
+                _ => exit.to_string(),
+            };
+
+            match room.get_door(exit) {
+                None => Some(name),
+                Some(door) if door.borrow().is_open() => Some(name),
+                Some(door) if door.borrow().is_noticeable() => Some(format!("{}(closed)", name)),
+                Some(_) => None,
+            }
+        })
+        .collect();
+    if exits.is_empty() {
+        text.push_str("\n[Exits: none]");
+    } else {
+        text.push_str(&format!("\n[Exits: {}]", exits.join(" ")));
+    }
+
+    let world = room.get_world().borrow();
+    for item in room.get_items().iter().filter_map(|id| world.get_item(*id)) {
+        text.push('\n');
+        text.push_str(item.borrow().get_long_description());
+    }
+
+    for char in world.get_characters_in_room(room) {
+        let char = char.borrow();
+
+        if char.get_id() != viewer {
+            let line = match char.get_short_description() {
+                "" => format!("{} is here.", char.get_name()),
+                short => format!("{}, {}, is here.", char.get_name(), short),
+            };
+            text.push('\n');
+            text.push_str(&line);
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{RoomId, World, WorldId};
+    use crate::game::Player;
+    use crate::item::Item;
+
+    #[test]
+    fn taking_from_a_nested_carried_container_does_not_count_the_weight_twice() {
+        let mut game = Game::new();
+        let world_id = game
+            .add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let world = game.get_world(world_id).unwrap().clone();
+        let room = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
+        world.borrow_mut().add_room(room.clone());
+
+        let player_id = game.add_player(Player::new(PlayerId::from(1))).unwrap();
+        let char = Character::new(world.borrow_mut().allocate_character_id());
+        let char_id = game
+            .spawn_player_character(world_id, player_id, char)
+            .unwrap();
+        let char = game.get_player_char(player_id).unwrap();
+
+        let (pouch, gem) = {
+            let mut world = world.borrow_mut();
+            let mut add = |keyword: &str, weight, container, location| {
+                let mut item = Item::new(
+                    world.allocate_item_id(),
+                    vec![String::from(keyword)],
+                    format!("a {}", keyword),
+                );
+                if container {
+                    item.set_flag(ItemFlag::Container);
+                }
+                item.set_weight(weight);
+                world.add_item(item, location).unwrap()
+            };
+            let bag = add("bag", 70, true, ItemLocation::Character(char_id));
+            let pouch = add("pouch", 0, true, ItemLocation::Container(bag));
+            let gem = add("gem", 20, false, ItemLocation::Container(pouch));
+            (pouch, gem)
+        };
+        assert!(world.borrow().get_carried_weight(char_id) + 20 > MAX_CARRY_WEIGHT);
+
+        let (pouch, gem) = {
+            let world = world.borrow();
+            (
+                world.get_item(pouch).unwrap().clone(),
+                world.get_item(gem).unwrap().clone(),
+            )
+        };
+        take_item(&mut game, player_id, &char, &room, &gem, Some(&pouch)).unwrap();
+
+        let location = gem.borrow().get_location();
+        assert_eq!(location, ItemLocation::Character(char_id));
+    }
+
+    #[test]
+    fn where_finds_characters_and_items_anywhere_in_the_world() {
+        let mut game = Game::new();
+        let world_id = game
+            .add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let world = game.get_world(world_id).unwrap().clone();
+        for (id, title) in [(1, "Hall"), (2, "Cellar")] {
+            let mut room = Room::new_in_void(RoomId::from(id), world.clone());
+            room.set_title(String::from(title));
+            world.borrow_mut().add_room(Rc::new(room));
+        }
+
+        let player_id = game.add_player(Player::new(PlayerId::from(1))).unwrap();
+        let char = Character::new(world.borrow_mut().allocate_character_id());
+        game.spawn_player_character(world_id, player_id, char)
+            .unwrap();
+
+        {
+            let mut world = world.borrow_mut();
+            let cellar = world.get_room(RoomId::from(2)).unwrap().clone();
+            let mut goblin = Character::new(world.allocate_character_id());
+            goblin.set_name(String::from("Grik"));
+            goblin.set_keywords(vec![String::from("goblin")]);
+            let goblin = world.add_character(goblin).unwrap();
+            world.teleport_character(goblin, &cellar);
+
+            let ring = Item::new(
+                world.allocate_item_id(),
+                vec![String::from("ring")],
+                String::from("a silver ring"),
+            );
+            world
+                .add_item(ring, ItemLocation::Character(goblin))
+                .unwrap();
+        }
+        game.take_output(player_id);
+
+        for (target, reply) in [
+            ("goblin", "Grik is in Cellar.\n"),
+            ("ring", "A silver ring is in Cellar.\n"),
+            ("dragon", "You cannot find any dragon in this world.\n"),
+        ] {
+            Where::new(Target::parse(target))
+                .execute(&mut game, player_id)
+                .unwrap();
+            assert_eq!(game.take_output(player_id).as_deref(), Some(reply));
+        }
+    }
 }
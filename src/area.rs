@@ -1,27 +1,39 @@
-use derive_more::{Display, From};
+use derive_more::{Display, From, Into};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
-use std::ops::Deref;
 use std::rc::{Rc, Weak};
 
-use crate::socium::{Character, CharacterId, CharacterRef};
-use crate::utils::{none_or_panic, BuildRef};
+use crate::action::GameAction;
+use crate::error::GameError;
+use crate::game::PlayerId;
+use crate::item::{Item, ItemId, ItemLocation, ItemRef};
+use crate::npc::{Behavior, NpcTemplate};
+use crate::script::ScriptDef;
+use crate::socium::{Character, CharacterId, CharacterRef, Stats, VitalPool};
+use crate::utils::BuildRef;
 
 pub trait Entity {}
 
 pub trait Teleportable {
-    fn move_from_to(&self, from: &Room, to: &Room) -> bool;
+    fn move_from_to(&mut self, from: &Rc<Room>, to: &Rc<Room>) -> bool;
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, From)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, From, Into)]
 pub struct WorldId(u32);
 
 pub struct World {
     id: WorldId,
     name: String,
     characters: HashMap<CharacterId, CharacterRef>,
+    next_character_id: u32,
+    items: HashMap<ItemId, ItemRef>,
+    next_item_id: u32,
     area: Vec<Rc<Room>>,
+    spawn_room: Option<RoomId>,
+    npc_templates: BTreeMap<u32, NpcTemplate>,
+    behaviors: HashMap<CharacterId, RefCell<Vec<Box<dyn Behavior>>>>,
 }
 
 impl World {
@@ -30,7 +42,13 @@ impl World {
             id,
             name,
             characters: HashMap::new(),
+            next_character_id: 1,
+            items: HashMap::new(),
+            next_item_id: 1,
             area: Vec::new(),
+            spawn_room: None,
+            npc_templates: BTreeMap::new(),
+            behaviors: HashMap::new(),
         }
     }
 
@@ -38,12 +56,27 @@ impl World {
         self.id
     }
 
-    pub fn add_character(&mut self, char: Character) -> CharacterId {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add_character(&mut self, char: Character) -> Result<CharacterId, GameError> {
         let id = char.get_id();
-        none_or_panic(
-            self.characters.insert(id, char.build_ref()),
-            "Character already exists",
-        );
+        if self.characters.contains_key(&id) {
+            return Err(GameError::DuplicateCharacter(id));
+        }
+        self.characters.insert(id, char.build_ref());
+
+        let raw_id: u32 = id.into();
+        self.next_character_id = self.next_character_id.max(raw_id + 1);
+
+        Ok(id)
+    }
+
+    /// Returns an id which is not taken by any character of the world yet.
+    pub fn allocate_character_id(&mut self) -> CharacterId {
+        let id = CharacterId::from(self.next_character_id);
+        self.next_character_id += 1;
 
         id
     }
@@ -52,28 +85,620 @@ impl World {
         self.characters.get(&id)
     }
 
+    pub fn get_characters(&self) -> impl Iterator<Item = &CharacterRef> {
+        self.characters.values()
+    }
+
+    /// Adds the item to the world and puts it to the location.
+    pub fn add_item(&mut self, item: Item, location: ItemLocation) -> Result<ItemId, GameError> {
+        let id = item.get_id();
+        if self.items.contains_key(&id) {
+            return Err(GameError::DuplicateItem(id));
+        }
+        self.items.insert(id, item.build_ref());
+
+        if let Err(error) = self.move_item(id, location) {
+            self.items.remove(&id);
+            return Err(error);
+        }
+
+        let raw_id: u32 = id.into();
+        self.next_item_id = self.next_item_id.max(raw_id + 1);
+
+        Ok(id)
+    }
+
+    /// Returns an id which is not taken by any item of the world yet.
+    pub fn allocate_item_id(&mut self) -> ItemId {
+        let id = ItemId::from(self.next_item_id);
+        self.next_item_id += 1;
+
+        id
+    }
+
+    pub fn get_item(&self, id: ItemId) -> Option<&ItemRef> {
+        self.items.get(&id)
+    }
+
+    pub fn require_item(&self, id: ItemId) -> Result<&ItemRef, GameError> {
+        self.get_item(id).ok_or(GameError::UnknownItem(id))
+    }
+
+    pub fn get_items(&self) -> impl Iterator<Item = &ItemRef> {
+        self.items.values()
+    }
+
+    /// Moves the item with everything inside it. The lists of both locations are updated.
+    pub fn move_item(&self, id: ItemId, to: ItemLocation) -> Result<(), GameError> {
+        let item = self.require_item(id)?.clone();
+
+        match to {
+            ItemLocation::Nowhere => {}
+            ItemLocation::Room(room_id) => {
+                self.get_room(room_id)
+                    .ok_or(GameError::UnknownRoom(room_id))?;
+            }
+            ItemLocation::Character(char_id) => {
+                self.get_character(char_id)
+                    .ok_or(GameError::UnknownCharacter(char_id))?;
+            }
+            ItemLocation::Container(container_id) => {
+                self.require_item(container_id)?;
+                if container_id == id || self.is_item_within(container_id, id) {
+                    return Err(GameError::ItemInsideItself(id));
+                }
+            }
+            ItemLocation::Equipped(char_id, slot) => {
+                let char = self
+                    .get_character(char_id)
+                    .ok_or(GameError::UnknownCharacter(char_id))?;
+                let taken = char
+                    .borrow()
+                    .get_equipped(slot)
+                    .is_some_and(|worn| worn != id);
+                if taken || !item.borrow().fits_slot(slot) {
+                    return Err(GameError::CannotEquip { item: id, slot });
+                }
+            }
+        }
+
+        let from = item.borrow().get_location();
+        self.detach_item(id, from);
+        self.attach_item(id, to);
+        item.borrow_mut().set_location(to);
+
+        Ok(())
+    }
+
+    /// Takes the item and everything inside it out of the world.
+    pub fn remove_item(&mut self, id: ItemId) -> Option<ItemRef> {
+        let location = self.get_item(id)?.borrow().get_location();
+        self.detach_item(id, location);
+
+        let item = self.items.remove(&id)?;
+        let contents = item.borrow().get_contents().to_vec();
+        for content_id in contents {
+            self.remove_item(content_id);
+        }
+        item.borrow_mut().set_location(ItemLocation::Nowhere);
+
+        Some(item)
+    }
+
+    /// Whether the item lies in the container, directly or inside other containers.
+    pub fn is_item_within(&self, id: ItemId, container_id: ItemId) -> bool {
+        let mut location = self.get_item(id).map(|item| item.borrow().get_location());
+
+        while let Some(ItemLocation::Container(outer_id)) = location {
+            if outer_id == container_id {
+                return true;
+            }
+            location = self
+                .get_item(outer_id)
+                .map(|item| item.borrow().get_location());
+        }
+
+        false
+    }
+
+    /// Whether the character holds the item: in the inventory, worn or inside anything of theirs.
+    pub fn is_item_carried_by(&self, id: ItemId, char_id: CharacterId) -> bool {
+        let mut location = self.get_item(id).map(|item| item.borrow().get_location());
+
+        loop {
+            match location {
+                Some(ItemLocation::Character(holder_id))
+                | Some(ItemLocation::Equipped(holder_id, _)) => return holder_id == char_id,
+                Some(ItemLocation::Container(outer_id)) => {
+                    location = self
+                        .get_item(outer_id)
+                        .map(|item| item.borrow().get_location());
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// The room the item is in, inside a container or with the character who holds it.
+    pub fn get_item_room(&self, id: ItemId) -> Option<Rc<Room>> {
+        let mut location = self.get_item(id).map(|item| item.borrow().get_location());
+
+        loop {
+            match location? {
+                ItemLocation::Room(room_id) => return self.get_room(room_id).cloned(),
+                ItemLocation::Character(holder_id) | ItemLocation::Equipped(holder_id, _) => {
+                    return self
+                        .get_character(holder_id)?
+                        .borrow()
+                        .get_current_room()
+                        .clone();
+                }
+                ItemLocation::Container(outer_id) => {
+                    location = self
+                        .get_item(outer_id)
+                        .map(|item| item.borrow().get_location());
+                }
+                ItemLocation::Nowhere => return None,
+            }
+        }
+    }
+
+    /// The weight of the item with everything inside it.
+    pub fn get_item_weight(&self, id: ItemId) -> u32 {
+        let Some(item) = self.get_item(id) else {
+            return 0;
+        };
+        let item = item.borrow();
+
+        item.get_contents()
+            .iter()
+            .fold(item.get_weight(), |sum, content_id| {
+                sum + self.get_item_weight(*content_id)
+            })
+    }
+
+    /// The weight of everything the character carries or wears.
+    pub fn get_carried_weight(&self, char_id: CharacterId) -> u32 {
+        let Some(char) = self.get_character(char_id) else {
+            return 0;
+        };
+        let char = char.borrow();
+
+        char.get_inventory()
+            .iter()
+            .chain(char.get_equipment().values())
+            .map(|id| self.get_item_weight(*id))
+            .sum()
+    }
+
+    /// The base stats of the character with the modifiers of everything they wear.
+    pub fn get_character_stats(&self, char_id: CharacterId) -> Stats {
+        let Some(char) = self.get_character(char_id) else {
+            return Stats::new();
+        };
+        let char = char.borrow();
+
+        let mut stats = char.get_base_stats().clone();
+        for item in char
+            .get_equipment()
+            .values()
+            .filter_map(|id| self.get_item(*id))
+        {
+            for (stat, value) in item.borrow().get_modifiers() {
+                stats.add(*stat, *value);
+            }
+        }
+
+        stats
+    }
+
+    fn detach_item(&self, id: ItemId, from: ItemLocation) {
+        match from {
+            ItemLocation::Nowhere => {}
+            ItemLocation::Room(room_id) => {
+                if let Some(room) = self.get_room(room_id) {
+                    room.remove_item(id);
+                }
+            }
+            ItemLocation::Character(char_id) => {
+                if let Some(char) = self.get_character(char_id) {
+                    char.borrow_mut().remove_from_inventory(id);
+                }
+            }
+            ItemLocation::Container(container_id) => {
+                if let Some(container) = self.get_item(container_id) {
+                    container.borrow_mut().remove_content(id);
+                }
+            }
+            ItemLocation::Equipped(char_id, slot) => {
+                if let Some(char) = self.get_character(char_id) {
+                    char.borrow_mut().unequip(slot);
+                }
+            }
+        }
+    }
+
+    fn attach_item(&self, id: ItemId, to: ItemLocation) {
+        match to {
+            ItemLocation::Nowhere => {}
+            ItemLocation::Room(room_id) => {
+                if let Some(room) = self.get_room(room_id) {
+                    room.add_item(id);
+                }
+            }
+            ItemLocation::Character(char_id) => {
+                if let Some(char) = self.get_character(char_id) {
+                    char.borrow_mut().add_to_inventory(id);
+                }
+            }
+            ItemLocation::Container(container_id) => {
+                if let Some(container) = self.get_item(container_id) {
+                    container.borrow_mut().add_content(id);
+                }
+            }
+            ItemLocation::Equipped(char_id, slot) => {
+                if let Some(char) = self.get_character(char_id) {
+                    char.borrow_mut().equip(slot, id);
+                }
+            }
+        }
+    }
+
     pub fn add_room(&mut self, room: Rc<Room>) {
         self.area.push(room);
     }
 
-    pub fn get_any_room(&self) -> Rc<Room> {
+    pub fn get_rooms(&self) -> &[Rc<Room>] {
+        &self.area
+    }
+
+    pub fn get_room(&self, id: RoomId) -> Option<&Rc<Room>> {
+        self.area.iter().find(|r| r.get_id() == id)
+    }
+
+    /// Makes a two-way passage: the exit from the first room and the opposite one back to it.
+    /// Nothing is linked when either exit already leads elsewhere.
+    pub fn link(
+        &self,
+        from: &Rc<Room>,
+        direction: MoveDirection,
+        to: &Rc<Room>,
+    ) -> Result<(), GameError> {
+        check_exit_free(to, &direction.opposite().into(), from)?;
+        self.link_one_way(from, direction, to)?;
+        self.link_one_way(to, direction.opposite(), from)
+    }
+
+    /// Makes an exit with no way back, like a chute or a portal. An exit which already leads
+    /// to the room is kept as it is, with its door.
+    pub fn link_one_way(
+        &self,
+        from: &Rc<Room>,
+        exit: impl Into<ExitKey>,
+        to: &Rc<Room>,
+    ) -> Result<(), GameError> {
+        for room in [from, to] {
+            if !self.area.iter().any(|r| Rc::ptr_eq(r, room)) {
+                return Err(GameError::UnknownRoom(room.get_id()));
+            }
+        }
+
+        let exit = exit.into();
+        check_exit_free(from, &exit, to)?;
+        if !room_exit_leads_to(from, &exit, to) {
+            from.set_exit(exit, to.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Finds the oddities of the room graph. None of them prevents playing, builders decide
+    /// whether they are intended. Rooms are reachable from the spawn room.
+    pub fn validate_geometry(&self) -> Vec<GeometryIssue> {
+        let mut issues = Vec::new();
+
+        for room in &self.area {
+            for (exit, room_exit) in room.get_exits() {
+                let Some(to) = room_exit.get_to() else {
+                    issues.push(GeometryIssue::DanglingExit {
+                        room: room.get_id(),
+                        exit,
+                    });
+                    continue;
+                };
+
+                let leads_back = match &exit {
+                    ExitKey::Direction(direction) => {
+                        room_exit_leads_to(&to, &direction.opposite().into(), room)
+                    }
+                    ExitKey::Keyword(_) => to.find_exit_to(room, None).is_some(),
+                };
+
+                if Rc::ptr_eq(&to, room) {
+                    issues.push(GeometryIssue::SelfLoop {
+                        room: room.get_id(),
+                        exit,
+                    });
+                } else if !leads_back {
+                    issues.push(GeometryIssue::AsymmetricExit {
+                        room: room.get_id(),
+                        exit,
+                        to: to.get_id(),
+                    });
+                }
+            }
+        }
+
+        let mut reached: HashSet<RoomId> = HashSet::new();
+        let mut queue: VecDeque<Rc<Room>> = self.get_spawn_room().ok().into_iter().collect();
+        while let Some(room) = queue.pop_front() {
+            if reached.insert(room.get_id()) {
+                queue.extend(room.get_pathways().into_iter().map(|(_, to)| to));
+            }
+        }
+
+        for room in &self.area {
+            if !reached.contains(&room.get_id()) {
+                issues.push(GeometryIssue::UnreachableRoom(room.get_id()));
+            }
+        }
+
+        issues
+    }
+
+    pub fn get_any_room(&self) -> Result<Rc<Room>, GameError> {
         match self.area.first() {
-            None => panic!("No rooms in the area"),
-            Some(r) => r.clone(),
+            None => Err(GameError::EmptyWorld(self.id)),
+            Some(r) => Ok(r.clone()),
+        }
+    }
+
+    pub fn get_spawn_room_id(&self) -> Option<RoomId> {
+        self.spawn_room
+    }
+
+    pub fn set_spawn_room(&mut self, room_id: RoomId) {
+        self.spawn_room = Some(room_id);
+    }
+
+    pub fn unset_spawn_room(&mut self) {
+        self.spawn_room = None;
+    }
+
+    /// Where characters come into the world and come back after death. Without a spawn point
+    /// it is the first room.
+    pub fn get_spawn_room(&self) -> Result<Rc<Room>, GameError> {
+        match self.spawn_room.and_then(|id| self.get_room(id)) {
+            Some(room) => Ok(room.clone()),
+            None => self.get_any_room(),
+        }
+    }
+
+    pub fn add_npc_template(&mut self, template: NpcTemplate) -> Result<(), GameError> {
+        if self.npc_templates.contains_key(&template.id) {
+            return Err(GameError::DuplicateNpcTemplate(template.id));
+        }
+        self.npc_templates.insert(template.id, template);
+
+        Ok(())
+    }
+
+    pub fn get_npc_template(&self, id: u32) -> Option<&NpcTemplate> {
+        self.npc_templates.get(&id)
+    }
+
+    pub fn get_npc_templates(&self) -> impl Iterator<Item = &NpcTemplate> {
+        self.npc_templates.values()
+    }
+
+    /// Creates a character from the template in the room.
+    pub fn spawn_npc(
+        &mut self,
+        template_id: u32,
+        room: &Rc<Room>,
+    ) -> Result<CharacterId, GameError> {
+        if !self.npc_templates.contains_key(&template_id) {
+            return Err(GameError::UnknownNpcTemplate(template_id));
         }
+
+        let mut char = Character::new(self.allocate_character_id());
+        let template = &self.npc_templates[&template_id];
+        for (stat, value) in &template.stats {
+            char.set_base_stat(*stat, *value);
+        }
+        for (vital, max) in &template.vitals {
+            *char.get_vital_mut(*vital) = VitalPool::new(*max);
+        }
+
+        let char_id = self.add_character(char)?;
+        self.make_npc(char_id, template_id)?;
+        self.teleport_character(char_id, room);
+
+        Ok(char_id)
+    }
+
+    /// Gives the character the name, the descriptions, the keywords and the behaviors of the
+    /// template. The stats and vitals stay as they are.
+    pub fn make_npc(&mut self, char_id: CharacterId, template_id: u32) -> Result<(), GameError> {
+        let template = self
+            .get_npc_template(template_id)
+            .ok_or(GameError::UnknownNpcTemplate(template_id))?;
+        let char = self
+            .get_character(char_id)
+            .ok_or(GameError::UnknownCharacter(char_id))?;
+
+        let mut char = char.borrow_mut();
+        char.set_name(template.name.clone());
+        char.set_short_description(template.short_description.clone());
+        char.set_keywords(template.keywords.clone());
+        char.set_description(template.description.clone());
+        char.set_template(template_id);
+        drop(char);
+
+        let behaviors = template.behaviors.iter().map(|def| def.build()).collect();
+        self.behaviors.insert(char_id, RefCell::new(behaviors));
+
+        Ok(())
+    }
+
+    /// Lets every NPC of the world decide what to do. The actions are to be queued by the game.
+    pub fn run_behaviors(&self) -> Vec<GameAction> {
+        let mut actions = Vec::new();
+
+        for (char_id, behaviors) in &self.behaviors {
+            let Some(npc) = self.get_character(*char_id) else {
+                continue;
+            };
+
+            let action = behaviors
+                .borrow_mut()
+                .iter_mut()
+                .find_map(|behavior| behavior.act(self, npc));
+            actions.extend(action);
+        }
+
+        actions
     }
 
-    pub fn spawn_character(&mut self, mut char: Character) -> CharacterId {
-        // TODO Choose a proper room for the spawn: the room of exit or all players hub.
-        let spawn_room = self.get_any_room();
+    /// Puts the character into the spawn room. Returning characters are placed in their saved
+    /// room by `Login::enter_saved` instead.
+    pub fn spawn_character(&mut self, mut char: Character) -> Result<CharacterId, GameError> {
+        let spawn_room = self.get_spawn_room()?;
 
         char.set_current_room(spawn_room.clone());
 
-        let char_id = self.add_character(char);
+        let char_id = self.add_character(char)?;
 
-        // TODO Add &char to the room's list.
+        spawn_room.add_occupant(char_id);
 
-        char_id
+        Ok(char_id)
+    }
+
+    /// Takes the character out of the world. What the character carries leaves with them.
+    pub fn despawn_character(&mut self, id: CharacterId) -> Option<CharacterRef> {
+        let belongings: Vec<ItemId> = {
+            let char = self.get_character(id)?.borrow();
+            let equipment = char.get_equipment().values().copied();
+            char.get_inventory()
+                .iter()
+                .copied()
+                .chain(equipment)
+                .collect()
+        };
+        for item_id in belongings {
+            self.remove_item(item_id);
+        }
+
+        self.behaviors.remove(&id);
+        let char = self.characters.remove(&id)?;
+
+        let mut char_mut = char.borrow_mut();
+        if let Some(room) = char_mut.get_current_room() {
+            room.remove_occupant(id);
+        }
+        char_mut.unset_current_room();
+        drop(char_mut);
+
+        Some(char)
+    }
+
+    /// Moves the character to the room of the world regardless of the exits.
+    pub fn teleport_character(&self, id: CharacterId, to: &Rc<Room>) -> bool {
+        let Some(char) = self.get_character(id) else {
+            return false;
+        };
+
+        let from = char.borrow().get_current_room().clone();
+
+        match from {
+            None => {
+                char.borrow_mut().set_current_room(to.clone());
+                to.add_occupant(id);
+                true
+            }
+            Some(from) => char.borrow_mut().move_from_to(&from, to),
+        }
+    }
+
+    pub fn get_characters_in_room(&self, room: &Room) -> Vec<CharacterRef> {
+        room.get_occupants()
+            .iter()
+            .filter_map(|id| self.get_character(*id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_players_in_room(&self, room: &Room) -> Vec<PlayerId> {
+        self.get_characters_in_room(room)
+            .iter()
+            .filter_map(|char| {
+                char.borrow()
+                    .get_owner()
+                    .as_ref()
+                    .map(|player| player.borrow().get_id())
+            })
+            .collect()
+    }
+}
+
+fn room_exit_leads_to(room: &Room, exit: &ExitKey, to: &Rc<Room>) -> bool {
+    matches!(room.get_exit_target(exit), Ok(Some(r)) if Rc::ptr_eq(&r, to))
+}
+
+/// Fails when the exit exists and leads anywhere but to the room.
+fn check_exit_free(room: &Room, exit: &ExitKey, to: &Rc<Room>) -> Result<(), GameError> {
+    if room.get_exit(exit).is_none() || room_exit_leads_to(room, exit, to) {
+        return Ok(());
+    }
+
+    Err(GameError::ExitTaken {
+        room: room.get_id(),
+        exit: exit.clone(),
+    })
+}
+
+#[derive(Debug)]
+pub enum GeometryIssue {
+    AsymmetricExit {
+        room: RoomId,
+        exit: ExitKey,
+        to: RoomId,
+    },
+    SelfLoop {
+        room: RoomId,
+        exit: ExitKey,
+    },
+    UnreachableRoom(RoomId),
+    DanglingExit {
+        room: RoomId,
+        exit: ExitKey,
+    },
+}
+
+impl fmt::Display for GeometryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryIssue::AsymmetricExit { room, exit, to } => write!(
+                f,
+                "exit {} of room {} leads to room {} which has no way back",
+                exit, room, to
+            ),
+            GeometryIssue::SelfLoop { room, exit } => {
+                write!(
+                    f,
+                    "exit {} of room {} leads back to the same room",
+                    exit, room
+                )
+            }
+            GeometryIssue::UnreachableRoom(room) => write!(f, "room {} cannot be reached", room),
+            GeometryIssue::DanglingExit { room, exit } => {
+                write!(
+                    f,
+                    "exit {} of room {} leads to a room which is gone",
+                    exit, room
+                )
+            }
+        }
     }
 }
 
@@ -95,23 +720,167 @@ impl fmt::Debug for World {
     }
 }
 
-pub enum RoomExit {
-    DeadEnd,
-    Pathway(Weak<Room>),
+/// What a player types to use an exit: a direction, or a keyword like `out` or `enter portal`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum ExitKey {
+    Direction(MoveDirection),
+    Keyword(String),
+}
+
+impl ExitKey {
+    /// Keywords are matched ignoring case and repeated spaces, so they are stored normalized.
+    pub fn keyword(keyword: &str) -> ExitKey {
+        let words: Vec<&str> = keyword.split_whitespace().collect();
+
+        ExitKey::Keyword(words.join(" ").to_lowercase())
+    }
+
+    /// Reads a direction name or abbreviation as the direction, anything else as a keyword.
+    pub fn from_name(name: &str) -> ExitKey {
+        match MoveDirection::from_name(name.trim()) {
+            Some(direction) => ExitKey::Direction(direction),
+            None => ExitKey::keyword(name),
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            ExitKey::Direction(direction) => direction.get_name(),
+            ExitKey::Keyword(keyword) => keyword,
+        }
+    }
+}
+
+impl From<MoveDirection> for ExitKey {
+    fn from(direction: MoveDirection) -> Self {
+        ExitKey::Direction(direction)
+    }
+}
+
+impl fmt::Display for ExitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DoorState {
+    Open,
+    Closed,
+    Locked,
+}
+
+/// A door between two rooms. Both sides of a two-way passage share the same door.
+#[derive(Debug)]
+pub struct Door {
+    name: String,
+    state: DoorState,
+    key: Option<ItemId>,
+    hidden: bool,
+    secret: bool,
+}
+
+impl Door {
+    pub fn new(name: String, state: DoorState) -> Self {
+        Self {
+            name,
+            state,
+            key: None,
+            hidden: false,
+            secret: false,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_state(&self) -> DoorState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: DoorState) {
+        self.state = state;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == DoorState::Open
+    }
+
+    pub fn get_key(&self) -> Option<ItemId> {
+        self.key
+    }
+
+    pub fn set_key(&mut self, key: ItemId) {
+        self.key = Some(key);
+    }
+
+    pub fn unset_key(&mut self) {
+        self.key = None;
+    }
+
+    /// A hidden door is left out of the exits while it is closed.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// A secret door is hidden, looks like a wall while closed and is not found by its name.
+    pub fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    pub fn set_secret(&mut self, secret: bool) {
+        self.secret = secret;
+    }
+
+    /// Whether players passing by notice the exit behind the door.
+    pub fn is_noticeable(&self) -> bool {
+        self.is_open() || !(self.hidden || self.secret)
+    }
+}
+
+impl BuildRef for Door {
+    fn build_ref(self) -> DoorRef {
+        Rc::new(RefCell::new(self))
+    }
 }
 
-#[derive(Debug, Display, PartialEq, Eq, Hash, Clone, Copy, From)]
+pub type DoorRef = Rc<RefCell<Door>>;
+
+#[derive(Clone)]
+pub struct RoomExit {
+    to: Weak<Room>,
+    door: Option<DoorRef>,
+}
+
+impl RoomExit {
+    pub fn get_to(&self) -> Option<Rc<Room>> {
+        self.to.upgrade()
+    }
+
+    pub fn get_door(&self) -> &Option<DoorRef> {
+        &self.door
+    }
+}
+
+#[derive(Debug, Display, PartialEq, Eq, Hash, Clone, Copy, From, Into)]
 pub struct RoomId(u32);
 
 pub struct Room {
     id: RoomId,
     world: WorldRef,
-    north_exit: RefCell<RoomExit>,
-    south_exit: RefCell<RoomExit>,
-    west_exit: RefCell<RoomExit>,
-    east_exit: RefCell<RoomExit>,
-    up_exit: RefCell<RoomExit>,
-    down_exit: RefCell<RoomExit>,
+    title: String,
+    description: String,
+    short_description: Option<String>,
+    zone: Option<String>,
+    scripts: Vec<ScriptDef>,
+    occupants: RefCell<Vec<CharacterId>>,
+    items: RefCell<Vec<ItemId>>,
+    exits: RefCell<BTreeMap<ExitKey, RoomExit>>,
 }
 
 impl Room {
@@ -119,12 +888,14 @@ impl Room {
         Room {
             id,
             world,
-            north_exit: RefCell::new(RoomExit::DeadEnd),
-            south_exit: RefCell::new(RoomExit::DeadEnd),
-            east_exit: RefCell::new(RoomExit::DeadEnd),
-            west_exit: RefCell::new(RoomExit::DeadEnd),
-            up_exit: RefCell::new(RoomExit::DeadEnd),
-            down_exit: RefCell::new(RoomExit::DeadEnd),
+            title: format!("Room #{}", id),
+            description: String::new(),
+            short_description: None,
+            zone: None,
+            scripts: Vec::new(),
+            occupants: RefCell::new(Vec::new()),
+            items: RefCell::new(Vec::new()),
+            exits: RefCell::new(BTreeMap::new()),
         }
     }
 
@@ -136,27 +907,198 @@ impl Room {
         &self.world
     }
 
-    pub fn get_exit(&self, direction: &MoveDirection) -> &RefCell<RoomExit> {
-        match direction {
-            MoveDirection::North => &self.north_exit,
-            MoveDirection::South => &self.south_exit,
-            MoveDirection::East => &self.east_exit,
-            MoveDirection::West => &self.west_exit,
-            MoveDirection::Up => &self.up_exit,
-            MoveDirection::Down => &self.down_exit,
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, description: String) {
+        self.description = description;
+    }
+
+    pub fn get_short_description(&self) -> &Option<String> {
+        &self.short_description
+    }
+
+    pub fn set_short_description(&mut self, short_description: String) {
+        self.short_description = Some(short_description);
+    }
+
+    pub fn unset_short_description(&mut self) {
+        self.short_description = None;
+    }
+
+    /// The part of the world the room belongs to, like a town or a forest. Wandering NPCs keep
+    /// to their zone.
+    pub fn get_zone(&self) -> &Option<String> {
+        &self.zone
+    }
+
+    pub fn set_zone(&mut self, zone: String) {
+        self.zone = Some(zone);
+    }
+
+    pub fn unset_zone(&mut self) {
+        self.zone = None;
+    }
+
+    pub fn get_scripts(&self) -> &[ScriptDef] {
+        &self.scripts
+    }
+
+    pub fn set_scripts(&mut self, scripts: Vec<ScriptDef>) {
+        self.scripts = scripts;
+    }
+
+    pub fn get_occupants(&self) -> Vec<CharacterId> {
+        self.occupants.borrow().clone()
+    }
+
+    pub fn has_occupant(&self, char_id: CharacterId) -> bool {
+        self.occupants.borrow().contains(&char_id)
+    }
+
+    pub fn add_occupant(&self, char_id: CharacterId) {
+        if !self.has_occupant(char_id) {
+            self.occupants.borrow_mut().push(char_id);
+        }
+    }
+
+    pub fn remove_occupant(&self, char_id: CharacterId) {
+        self.occupants.borrow_mut().retain(|id| *id != char_id);
+    }
+
+    /// The items lying on the floor, in the order they were put there.
+    pub fn get_items(&self) -> Vec<ItemId> {
+        self.items.borrow().clone()
+    }
+
+    pub(crate) fn add_item(&self, item_id: ItemId) {
+        if !self.items.borrow().contains(&item_id) {
+            self.items.borrow_mut().push(item_id);
+        }
+    }
+
+    pub(crate) fn remove_item(&self, item_id: ItemId) {
+        self.items.borrow_mut().retain(|id| *id != item_id);
+    }
+
+    pub fn get_exit(&self, exit: &ExitKey) -> Option<RoomExit> {
+        self.exits.borrow().get(exit).cloned()
+    }
+
+    /// Returns the room the exit leads to, or `None` for a dead end.
+    pub fn get_exit_target(&self, exit: &ExitKey) -> Result<Option<Rc<Room>>, GameError> {
+        match self.exits.borrow().get(exit) {
+            None => Ok(None),
+            Some(room_exit) => match room_exit.get_to() {
+                None => Err(GameError::DanglingExit {
+                    room: self.id,
+                    exit: exit.clone(),
+                }),
+                Some(r) => Ok(Some(r)),
+            },
+        }
+    }
+
+    pub fn get_exits(&self) -> Vec<(ExitKey, RoomExit)> {
+        self.exits
+            .borrow()
+            .iter()
+            .map(|(exit, room_exit)| (exit.clone(), room_exit.clone()))
+            .collect()
+    }
+
+    /// Returns the exits which lead to an existing room, directions first.
+    pub fn get_pathways(&self) -> Vec<(ExitKey, Rc<Room>)> {
+        self.exits
+            .borrow()
+            .iter()
+            .filter_map(|(exit, room_exit)| room_exit.get_to().map(|r| (exit.clone(), r)))
+            .collect()
+    }
+
+    pub fn get_door(&self, exit: &ExitKey) -> Option<DoorRef> {
+        self.exits.borrow().get(exit)?.door.clone()
+    }
+
+    /// Finds the exit with a door of the name. Secret doors are never found by their name.
+    pub fn find_door(&self, name: &str) -> Option<ExitKey> {
+        self.exits
+            .borrow()
+            .iter()
+            .find(|(_, room_exit)| match &room_exit.door {
+                None => false,
+                Some(door) => {
+                    let door = door.borrow();
+                    !door.is_secret() && door.get_name().eq_ignore_ascii_case(name)
+                }
+            })
+            .map(|(exit, _)| exit.clone())
+    }
+
+    /// Finds the exit which leads to the room, preferring the given one when there are several.
+    pub fn find_exit_to(&self, to: &Room, preferred: Option<&ExitKey>) -> Option<ExitKey> {
+        let exits = self.exits.borrow();
+        let leads_to = |room_exit: &RoomExit| room_exit.get_to().is_some_and(|r| *r == *to);
+
+        if let Some(preferred) = preferred
+            && exits.get(preferred).is_some_and(leads_to)
+        {
+            return Some(preferred.clone());
+        }
+
+        exits
+            .iter()
+            .find(|(_, room_exit)| leads_to(room_exit))
+            .map(|(exit, _)| exit.clone())
+    }
+
+    /// Finds the keyword exit the input names, if the input is nothing but the keyword.
+    pub fn find_exit_keyword(&self, input: &str) -> Option<ExitKey> {
+        let key = ExitKey::keyword(input);
+
+        match &key {
+            ExitKey::Keyword(keyword) if keyword.is_empty() => None,
+            _ => self.exits.borrow().contains_key(&key).then_some(key),
         }
     }
 
-    pub fn set_exit(&self, direction: &MoveDirection, to_room: Rc<Room>) {
-        let exit_ref = self.get_exit(direction);
+    pub fn set_exit(&self, exit: ExitKey, to_room: Rc<Room>) {
+        let room_exit = RoomExit {
+            to: Rc::downgrade(&to_room),
+            door: None,
+        };
 
-        *exit_ref.borrow_mut() = RoomExit::Pathway(Rc::downgrade(&to_room));
+        self.exits.borrow_mut().insert(exit, room_exit);
     }
 
-    pub fn set_dead_end(&self, direction: &MoveDirection) {
-        let exit_ref = self.get_exit(direction);
+    /// Puts the door on the exit. Returns false when there is no such exit.
+    pub fn set_door(&self, exit: &ExitKey, door: DoorRef) -> bool {
+        match self.exits.borrow_mut().get_mut(exit) {
+            None => false,
+            Some(room_exit) => {
+                room_exit.door = Some(door);
+                true
+            }
+        }
+    }
 
-        *exit_ref.borrow_mut() = RoomExit::DeadEnd;
+    pub fn unset_door(&self, exit: &ExitKey) {
+        if let Some(room_exit) = self.exits.borrow_mut().get_mut(exit) {
+            room_exit.door = None;
+        }
+    }
+
+    pub fn set_dead_end(&self, exit: &ExitKey) {
+        self.exits.borrow_mut().remove(exit);
     }
 }
 
@@ -170,31 +1112,33 @@ impl Eq for Room {}
 
 impl fmt::Debug for Room {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fn get_exit_caption(rc: &RefCell<RoomExit>) -> String {
-            match rc.borrow().deref() {
-                RoomExit::DeadEnd => String::from("#"),
-                RoomExit::Pathway(wr) => match wr.upgrade() {
-                    None => panic!("No reference on a room exit"),
+        let exits: Vec<String> = self
+            .exits
+            .borrow()
+            .iter()
+            .map(|(exit, room_exit)| {
+                let to = match room_exit.get_to() {
+                    None => String::from("?"),
                     Some(r) => r.id.to_string(),
-                },
-            }
-        }
+                };
+
+                match &room_exit.door {
+                    None => format!("{}->{}", exit, to),
+                    Some(door) => format!("{}->{} ({:?})", exit, to, door.borrow().get_state()),
+                }
+            })
+            .collect();
 
         write!(
             f,
-            "Room {{ id: {}, exits (NSWEUD): {}-{}-{}-{}-{}-{} }}",
+            "Room {{ id: {}, exits: [{}] }}",
             self.id,
-            get_exit_caption(&self.north_exit),
-            get_exit_caption(&self.south_exit),
-            get_exit_caption(&self.west_exit),
-            get_exit_caption(&self.east_exit),
-            get_exit_caption(&self.up_exit),
-            get_exit_caption(&self.down_exit)
+            exits.join(", ")
         )
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub enum MoveDirection {
     North,
     South,
@@ -202,4 +1146,177 @@ pub enum MoveDirection {
     West,
     Up,
     Down,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl MoveDirection {
+    pub const ALL: [MoveDirection; 10] = [
+        MoveDirection::North,
+        MoveDirection::South,
+        MoveDirection::East,
+        MoveDirection::West,
+        MoveDirection::Up,
+        MoveDirection::Down,
+        MoveDirection::NorthEast,
+        MoveDirection::NorthWest,
+        MoveDirection::SouthEast,
+        MoveDirection::SouthWest,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            MoveDirection::North => "north",
+            MoveDirection::South => "south",
+            MoveDirection::East => "east",
+            MoveDirection::West => "west",
+            MoveDirection::Up => "up",
+            MoveDirection::Down => "down",
+            MoveDirection::NorthEast => "northeast",
+            MoveDirection::NorthWest => "northwest",
+            MoveDirection::SouthEast => "southeast",
+            MoveDirection::SouthWest => "southwest",
+        }
+    }
+
+    pub fn get_abbreviation(&self) -> &'static str {
+        match self {
+            MoveDirection::North => "n",
+            MoveDirection::South => "s",
+            MoveDirection::East => "e",
+            MoveDirection::West => "w",
+            MoveDirection::Up => "u",
+            MoveDirection::Down => "d",
+            MoveDirection::NorthEast => "ne",
+            MoveDirection::NorthWest => "nw",
+            MoveDirection::SouthEast => "se",
+            MoveDirection::SouthWest => "sw",
+        }
+    }
+
+    /// Finds the direction by its full name or abbreviation, like `northeast` or `ne`.
+    pub fn from_name(name: &str) -> Option<MoveDirection> {
+        let name = name.to_ascii_lowercase();
+
+        MoveDirection::ALL
+            .into_iter()
+            .find(|d| d.get_name() == name || d.get_abbreviation() == name)
+    }
+
+    /// The direction which leads back, so that a pathway to the north usually returns south.
+    pub fn opposite(&self) -> MoveDirection {
+        match self {
+            MoveDirection::North => MoveDirection::South,
+            MoveDirection::South => MoveDirection::North,
+            MoveDirection::East => MoveDirection::West,
+            MoveDirection::West => MoveDirection::East,
+            MoveDirection::Up => MoveDirection::Down,
+            MoveDirection::Down => MoveDirection::Up,
+            MoveDirection::NorthEast => MoveDirection::SouthWest,
+            MoveDirection::NorthWest => MoveDirection::SouthEast,
+            MoveDirection::SouthEast => MoveDirection::NorthWest,
+            MoveDirection::SouthWest => MoveDirection::NorthEast,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::WearSlot;
+    use crate::utils::BuildRef;
+
+    /// A world with three rooms and no exits.
+    fn three_rooms() -> (WorldRef, Vec<Rc<Room>>) {
+        let world = World::new(WorldId::from(1), String::from("Test")).build_ref();
+        let rooms: Vec<Rc<Room>> = (1..=3)
+            .map(|id| Rc::new(Room::new_in_void(RoomId::from(id), world.clone())))
+            .collect();
+        for room in &rooms {
+            world.borrow_mut().add_room(room.clone());
+        }
+
+        (world, rooms)
+    }
+
+    #[test]
+    fn reachability_starts_at_the_spawn_room() {
+        let (world, rooms) = three_rooms();
+        let mut world = world.borrow_mut();
+        world
+            .link(&rooms[1], MoveDirection::North, &rooms[2])
+            .unwrap();
+        world.set_spawn_room(rooms[1].get_id());
+
+        let issues = world.validate_geometry();
+        assert!(matches!(
+            issues.as_slice(),
+            [GeometryIssue::UnreachableRoom(id)] if *id == rooms[0].get_id()
+        ));
+    }
+
+    #[test]
+    fn link_refuses_to_overwrite_an_exit() {
+        let (world, rooms) = three_rooms();
+        let world = world.borrow();
+        world
+            .link(&rooms[0], MoveDirection::North, &rooms[1])
+            .unwrap();
+        world
+            .link(&rooms[0], MoveDirection::North, &rooms[1])
+            .unwrap();
+
+        let taken = world.link(&rooms[2], MoveDirection::North, &rooms[1]);
+        assert!(matches!(
+            taken,
+            Err(GameError::ExitTaken { room, .. }) if room == rooms[1].get_id()
+        ));
+        assert!(rooms[2].get_exits().is_empty());
+
+        let taken = world.link(&rooms[0], MoveDirection::North, &rooms[2]);
+        assert!(matches!(
+            taken,
+            Err(GameError::ExitTaken { room, .. }) if room == rooms[0].get_id()
+        ));
+        assert!(rooms[2].get_exits().is_empty());
+        assert!(room_exit_leads_to(
+            &rooms[1],
+            &MoveDirection::South.into(),
+            &rooms[0]
+        ));
+    }
+
+    #[test]
+    fn carried_items_include_worn_and_bagged_ones() {
+        let mut world = World::new(WorldId::from(1), String::from("Test"));
+        let owner = world
+            .add_character(Character::new(CharacterId::from(1)))
+            .unwrap();
+        let other = world
+            .add_character(Character::new(CharacterId::from(2)))
+            .unwrap();
+
+        let mut add = |location| {
+            let mut item = Item::new(
+                world.allocate_item_id(),
+                Vec::new(),
+                String::from("a thing"),
+            );
+            item.set_slots(vec![WearSlot::Hands]);
+            world.add_item(item, location).unwrap()
+        };
+        let bag = add(ItemLocation::Character(owner));
+        let pouch = add(ItemLocation::Container(bag));
+        let key = add(ItemLocation::Container(pouch));
+        let ring = add(ItemLocation::Equipped(owner, WearSlot::Hands));
+        let lost = add(ItemLocation::Nowhere);
+
+        for id in [bag, key, ring] {
+            assert!(world.is_item_carried_by(id, owner));
+            assert!(!world.is_item_carried_by(id, other));
+        }
+        assert!(!world.is_item_carried_by(lost, owner));
+    }
 }
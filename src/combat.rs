@@ -0,0 +1,565 @@
+use crate::action::{
+    CharAction, GameAction, WALK_MOVEMENT_COST, describe_way, locate_subject, render_room,
+    resolve_walk,
+};
+use crate::area::{ExitKey, Room, World, WorldRef};
+use crate::error::GameError;
+use crate::game::{Game, GameEvent, PlayerId};
+use crate::item::{Item, ItemFlag, ItemId, ItemLocation, capitalize};
+use crate::socium::{CharacterId, CharacterRef, Stat, Vital, VitalPool};
+use crate::target::{Target, find_character};
+use rand::seq::IndexedRandom;
+use std::rc::Rc;
+
+/// The chance to hit, in percent, between two equal opponents without armor.
+const BASE_HIT_CHANCE: i32 = 70;
+
+/// The chance to get away from a fight, in percent.
+const FLEE_CHANCE: u32 = 50;
+
+const CORPSE_WEIGHT: u32 = 80;
+
+/// How long a corpse lies before it rots away.
+const CORPSE_DECAY_SECONDS: u64 = 300;
+
+// ----------------------------------------------------------------------------------------------------
+// Character actions
+// ----------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub struct Kill {
+    target: Target,
+}
+
+#[derive(Debug)]
+pub struct Flee {}
+
+impl Kill {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
+
+impl CharAction for Kill {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+
+        if char.borrow().is_fighting() {
+            game.send_to_player(subject_id, "You are already fighting!");
+            return Ok(());
+        }
+
+        let found = find_character(&room.get_world().borrow(), &room, &self.target);
+        let Some(victim) = found else {
+            game.send_to_player(subject_id, &format!("You see no {} here.", self.target));
+            return Ok(());
+        };
+        if Rc::ptr_eq(&char, &victim) {
+            game.send_to_player(subject_id, "You cannot attack yourself.");
+            return Ok(());
+        }
+
+        game.queue_action(GameAction::Engage {
+            attacker: char,
+            victim,
+        });
+
+        Ok(())
+    }
+}
+
+impl CharAction for Flee {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        let Some((char, room)) = locate_subject(game, subject_id) else {
+            return Ok(());
+        };
+
+        if !char.borrow().is_fighting() {
+            game.send_to_player(subject_id, "You are not fighting anyone.");
+            return Ok(());
+        }
+
+        if char.borrow().get_vital(Vital::Movement).get_current() < WALK_MOVEMENT_COST {
+            game.send_to_player(subject_id, "You are too exhausted to flee.");
+            return Ok(());
+        }
+
+        let ways: Vec<_> = room
+            .get_pathways()
+            .into_iter()
+            .filter(|(exit, _)| {
+                room.get_door(exit)
+                    .is_none_or(|door| door.borrow().is_open())
+            })
+            .collect();
+        let Some((exit, to)) = ways.choose(&mut rand::rng()).cloned() else {
+            game.send_to_player(subject_id, "There is nowhere to run!");
+            return Ok(());
+        };
+
+        let char_name = char.borrow().get_name();
+
+        if rand::random_range(0..100) >= FLEE_CHANCE {
+            game.send_to_player(subject_id, "You fail to get away!");
+            game.send_to_room(
+                &room,
+                Some(subject_id),
+                &format!("{} tries to flee, but fails.", char_name),
+            );
+            return Ok(());
+        }
+
+        game.queue_action(GameAction::Flee {
+            who: char,
+            from: room,
+            to,
+            exit,
+        });
+
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Rounds
+// ----------------------------------------------------------------------------------------------------
+
+/// Starts the fight. Anything could change since the fight was decided on, so nothing happens
+/// unless both are still in the same room and the attacker is not busy with another fight.
+pub(crate) fn resolve_engage(game: &mut Game, attacker: CharacterRef, victim: CharacterRef) {
+    if Rc::ptr_eq(&attacker, &victim) || attacker.borrow().is_fighting() {
+        return;
+    }
+
+    let room = attacker.borrow().get_current_room().clone();
+    let victim_room = victim.borrow().get_current_room().clone();
+    let room = match (room, victim_room) {
+        (Some(room), Some(victim_room)) if Rc::ptr_eq(&room, &victim_room) => room,
+        _ => return,
+    };
+
+    let attacker_name = attacker.borrow().get_name();
+    let victim_name = victim.borrow().get_name();
+    let victim_id = victim.borrow().get_id();
+
+    attacker.borrow_mut().set_fighting(victim_id);
+
+    send_to_fight(
+        game,
+        &room,
+        &attacker,
+        &victim,
+        [
+            &format!("You attack {}!", victim_name),
+            &format!("{} attacks you!", attacker_name),
+            &format!("{} attacks {}!", attacker_name, victim_name),
+        ],
+    );
+
+    // The first blow does not wait for the next round.
+    resolve_attack(game, attacker, victim);
+}
+
+/// Strikes the victim once. The fight ends quietly when the two are not in the same room anymore.
+pub(crate) fn resolve_attack(game: &mut Game, attacker: CharacterRef, victim: CharacterRef) {
+    let victim_id = victim.borrow().get_id();
+    if attacker.borrow().get_fighting() != Some(victim_id) {
+        return;
+    }
+
+    let room = attacker.borrow().get_current_room().clone();
+    let victim_room = victim.borrow().get_current_room().clone();
+    let room = match (room, victim_room) {
+        (Some(room), Some(victim_room)) if Rc::ptr_eq(&room, &victim_room) => room,
+        _ => {
+            attacker.borrow_mut().unset_fighting();
+            return;
+        }
+    };
+
+    if victim.borrow().get_vital(Vital::HitPoints).get_current() <= 0 {
+        return;
+    }
+
+    let world = room.get_world().clone();
+    let (attacker_stats, victim_stats) = {
+        let world = world.borrow();
+        (
+            world.get_character_stats(attacker.borrow().get_id()),
+            world.get_character_stats(victim_id),
+        )
+    };
+
+    let attacker_name = attacker.borrow().get_name();
+    let victim_name = victim.borrow().get_name();
+
+    if !victim.borrow().is_fighting() {
+        victim.borrow_mut().set_fighting(attacker.borrow().get_id());
+    }
+
+    let hit_chance = (BASE_HIT_CHANCE
+        + (attacker_stats.get(Stat::Dexterity) - victim_stats.get(Stat::Dexterity)) * 3
+        - victim_stats.get(Stat::Armor))
+    .clamp(5, 95);
+
+    if rand::random_range(0..100) >= hit_chance {
+        send_to_fight(
+            game,
+            &room,
+            &attacker,
+            &victim,
+            [
+                &format!("You miss {}.", victim_name),
+                &format!("{} misses you.", attacker_name),
+                &format!("{} misses {}.", attacker_name, victim_name),
+            ],
+        );
+        return;
+    }
+
+    let damage = (rand::random_range(1..=4)
+        + attacker_stats.get(Stat::Damage)
+        + (attacker_stats.get(Stat::Strength) - 10) / 2)
+        .max(1);
+    victim
+        .borrow_mut()
+        .get_vital_mut(Vital::HitPoints)
+        .lose(damage);
+
+    send_to_fight(
+        game,
+        &room,
+        &attacker,
+        &victim,
+        [
+            &format!("You hit {}.", victim_name),
+            &format!("{} hits you.", attacker_name),
+            &format!("{} hits {}.", attacker_name, victim_name),
+        ],
+    );
+
+    if victim.borrow().get_vital(Vital::HitPoints).get_current() <= 0 {
+        kill_character(game, &room, victim, attacker);
+    }
+}
+
+/// Leaves a corpse with everything the character had. A player comes back at the spawn point of
+/// the world, anyone else is gone.
+fn kill_character(game: &mut Game, room: &Rc<Room>, victim: CharacterRef, killer: CharacterRef) {
+    let (victim_id, victim_name, owner_id) = {
+        let victim = victim.borrow();
+        let owner_id = victim.get_owner().as_ref().map(|p| p.borrow().get_id());
+        (victim.get_id(), victim.get_name(), owner_id)
+    };
+
+    if let Some(owner_id) = owner_id {
+        game.send_to_player(owner_id, "You are dead!");
+    }
+    game.send_to_room(room, owner_id, &format!("{} is dead!", victim_name));
+    game.publish(GameEvent::CharacterDied {
+        who: victim.clone(),
+        room: room.clone(),
+        killer: Some(killer),
+    });
+
+    let world = room.get_world().clone();
+    stop_fighting(&world.borrow(), victim_id);
+
+    let corpse = leave_corpse(&mut world.borrow_mut(), room, &victim);
+    match corpse {
+        Ok(corpse) => schedule_corpse_decay(game, &world, corpse),
+        Err(error) => eprintln!(">>> Cannot leave the corpse of {}: {error}", victim_name),
+    }
+
+    let Some(owner_id) = owner_id else {
+        world.borrow_mut().despawn_character(victim_id);
+        return;
+    };
+
+    let spawn_room = match world.borrow().get_spawn_room() {
+        Ok(spawn_room) => spawn_room,
+        Err(error) => {
+            eprintln!(">>> Cannot bring {} back: {error}", victim_name);
+            return;
+        }
+    };
+    world.borrow().teleport_character(victim_id, &spawn_room);
+
+    for vital in Vital::ALL {
+        let max = victim.borrow().get_vital(vital).get_max();
+        *victim.borrow_mut().get_vital_mut(vital) = VitalPool::new(max);
+    }
+
+    game.send_to_room(
+        &spawn_room,
+        Some(owner_id),
+        &format!("{} appears.", victim_name),
+    );
+    game.send_to_player(owner_id, "You come back to life.");
+    game.send_to_player(owner_id, &render_room(&spawn_room, victim_id, false));
+}
+
+fn leave_corpse(
+    world: &mut World,
+    room: &Room,
+    victim: &CharacterRef,
+) -> Result<ItemId, GameError> {
+    let victim = victim.borrow();
+
+    let corpse_id = world.allocate_item_id();
+    let mut corpse = Item::new(
+        corpse_id,
+        vec![String::from("corpse")],
+        format!("the corpse of {}", victim.get_name()),
+    );
+    corpse.set_weight(CORPSE_WEIGHT);
+    corpse.set_flag(ItemFlag::Container);
+    corpse.set_flag(ItemFlag::NoTake);
+    corpse.set_flag(ItemFlag::Corpse);
+    world.add_item(corpse, ItemLocation::Room(room.get_id()))?;
+
+    let belongings: Vec<_> = victim
+        .get_inventory()
+        .iter()
+        .chain(victim.get_equipment().values())
+        .copied()
+        .collect();
+    drop(victim);
+
+    for item_id in belongings {
+        world.move_item(item_id, ItemLocation::Container(corpse_id))?;
+    }
+
+    Ok(corpse_id)
+}
+
+/// Ends the fight and tells everyone about the escape once the character got away. A failed walk
+/// leaves them fighting.
+pub(crate) fn resolve_flee(
+    game: &mut Game,
+    who: CharacterRef,
+    from: Rc<Room>,
+    to: Rc<Room>,
+    exit: ExitKey,
+) {
+    resolve_walk(game, who.clone(), from.clone(), to.clone(), exit.clone());
+
+    let got_away = who
+        .borrow()
+        .get_current_room()
+        .as_ref()
+        .is_some_and(|room| Rc::ptr_eq(room, &to));
+    if got_away {
+        let (char_id, owner_id, char_name) = {
+            let char_ref = who.borrow();
+            let owner_id = char_ref.get_owner().as_ref().map(|p| p.borrow().get_id());
+            (char_ref.get_id(), owner_id, char_ref.get_name())
+        };
+        stop_fighting(&to.get_world().borrow(), char_id);
+
+        if let Some(owner_id) = owner_id {
+            game.send_to_player(owner_id, &format!("You flee {}!", describe_way(&exit)));
+        }
+        game.send_to_room(&from, owner_id, &format!("{} panics and flees!", char_name));
+    }
+}
+
+/// Schedules the decay of every corpse in the game, like the ones restored from a snapshot.
+pub fn schedule_corpse_decays(game: &mut Game) {
+    let corpses: Vec<(WorldRef, ItemId)> = game
+        .get_worlds()
+        .flat_map(|world| {
+            world
+                .borrow()
+                .get_items()
+                .filter(|item| item.borrow().has_flag(ItemFlag::Corpse))
+                .map(|item| (world.clone(), item.borrow().get_id()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    for (world, corpse) in corpses {
+        schedule_corpse_decay(game, &world, corpse);
+    }
+}
+
+fn schedule_corpse_decay(game: &mut Game, world: &WorldRef, corpse: ItemId) {
+    let delay = game.get_clock().ticks_in(CORPSE_DECAY_SECONDS);
+    game.schedule_action(
+        delay,
+        GameAction::Decay {
+            world: world.clone(),
+            corpse,
+        },
+    );
+}
+
+/// Rots the corpse away, unless someone got rid of it already. Whatever it held stays behind.
+pub(crate) fn resolve_decay(game: &mut Game, world: WorldRef, corpse_id: ItemId) {
+    let Some(corpse) = world.borrow().get_item(corpse_id).cloned() else {
+        return;
+    };
+    let (location, contents, name) = {
+        let corpse = corpse.borrow();
+        let name = capitalize(corpse.get_short_description());
+        (corpse.get_location(), corpse.get_contents().to_vec(), name)
+    };
+
+    let mut world = world.borrow_mut();
+    for item_id in contents {
+        if let Err(error) = world.move_item(item_id, location) {
+            eprintln!(">>> Cannot take item {} out of {}: {error}", item_id, name);
+        }
+    }
+    world.remove_item(corpse_id);
+
+    if let ItemLocation::Room(room_id) = location
+        && let Some(room) = world.get_room(room_id).cloned()
+    {
+        drop(world);
+        game.send_to_room(&room, None, &format!("{} rots away.", name));
+    }
+}
+
+/// Ends the fights of the character and of everyone fighting them.
+fn stop_fighting(world: &World, char_id: CharacterId) {
+    for char in world.get_characters() {
+        let mut char = char.borrow_mut();
+
+        if char.get_id() == char_id || char.get_fighting() == Some(char_id) {
+            char.unset_fighting();
+        }
+    }
+}
+
+/// Tells the attacker, the victim and the rest of the room about the blow, each in their words.
+fn send_to_fight(
+    game: &mut Game,
+    room: &Room,
+    attacker: &CharacterRef,
+    victim: &CharacterRef,
+    texts: [&str; 3],
+) {
+    let owner_of = |char: &CharacterRef| {
+        char.borrow()
+            .get_owner()
+            .as_ref()
+            .map(|p| p.borrow().get_id())
+    };
+    let attacker_id = owner_of(attacker);
+    let victim_id = owner_of(victim);
+
+    let recipients = room.get_world().borrow().get_players_in_room(room);
+    for player_id in recipients {
+        let text = if Some(player_id) == attacker_id {
+            texts[0]
+        } else if Some(player_id) == victim_id {
+            texts[1]
+        } else {
+            texts[2]
+        };
+        game.send_to_player(player_id, text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{Door, DoorState, MoveDirection, RoomId, WorldId};
+    use crate::game::Player;
+    use crate::socium::Character;
+    use crate::utils::BuildRef;
+
+    #[test]
+    fn decayed_corpse_leaves_its_contents_in_the_room() {
+        let mut game = Game::new();
+        let world_id = game
+            .add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let world = game.get_world(world_id).unwrap().clone();
+        let room = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
+        world.borrow_mut().add_room(room.clone());
+
+        let (corpse, sword) = {
+            let mut world = world.borrow_mut();
+            let mut corpse = Item::new(
+                world.allocate_item_id(),
+                vec![String::from("corpse")],
+                String::from("the corpse of a goblin"),
+            );
+            corpse.set_flag(ItemFlag::Container);
+            corpse.set_flag(ItemFlag::Corpse);
+            let corpse = world
+                .add_item(corpse, ItemLocation::Room(room.get_id()))
+                .unwrap();
+            let sword = Item::new(
+                world.allocate_item_id(),
+                vec![String::from("sword")],
+                String::from("a sword"),
+            );
+            let sword = world
+                .add_item(sword, ItemLocation::Container(corpse))
+                .unwrap();
+            (corpse, sword)
+        };
+
+        GameAction::Decay {
+            world: world.clone(),
+            corpse,
+        }
+        .resolve(&mut game);
+
+        let world = world.borrow();
+        assert!(world.get_item(corpse).is_none());
+        assert_eq!(room.get_items(), [sword]);
+        let location = world.get_item(sword).unwrap().borrow().get_location();
+        assert_eq!(location, ItemLocation::Room(room.get_id()));
+    }
+
+    #[test]
+    fn flee_through_a_door_closed_meanwhile_keeps_fighting_quietly() {
+        let mut game = Game::new();
+        let world_id = game
+            .add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let world = game.get_world(world_id).unwrap().clone();
+        let hall = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
+        let yard = Rc::new(Room::new_in_void(RoomId::from(2), world.clone()));
+        world.borrow_mut().add_room(hall.clone());
+        world.borrow_mut().add_room(yard.clone());
+        world
+            .borrow()
+            .link(&hall, MoveDirection::North, &yard)
+            .unwrap();
+        let door = Door::new(String::from("gate"), DoorState::Closed).build_ref();
+        hall.set_door(&MoveDirection::North.into(), door);
+
+        let player_id = game.add_player(Player::new(PlayerId::from(1))).unwrap();
+        let mut char = Character::new(world.borrow_mut().allocate_character_id());
+        char.set_name(String::from("Bob"));
+        game.spawn_player_character(world_id, player_id, char)
+            .unwrap();
+        let char = game.get_player_char(player_id).unwrap();
+        char.borrow_mut().set_fighting(CharacterId::from(99));
+        game.take_output(player_id);
+
+        GameAction::Flee {
+            who: char.clone(),
+            from: hall.clone(),
+            to: yard,
+            exit: MoveDirection::North.into(),
+        }
+        .resolve(&mut game);
+
+        assert!(Rc::ptr_eq(
+            char.borrow().get_current_room().as_ref().unwrap(),
+            &hall
+        ));
+        assert!(char.borrow().is_fighting());
+        let output = game.take_output(player_id).unwrap();
+        assert!(output.contains("The gate has closed before you."));
+        assert!(!output.contains("You flee"));
+    }
+}
@@ -0,0 +1,626 @@
+use crate::action::{
+    CharAction, DoorOperation, DropItem, Empty, Equipment, Examine, GetItem, Inventory, Look, Move,
+    OperateDoor, PutItem, Quit, Remove, Say, Score, UnknownCommand, Wear, Where, Wield,
+};
+use crate::area::MoveDirection;
+use crate::combat::{Flee, Kill};
+use crate::error::GameError;
+use crate::game::{Game, PlayerId};
+use crate::target::Target;
+
+/// Builds the action of a command from the registry it was found in and its argument text.
+pub type CommandHandler = fn(&CommandRegistry, &str) -> Box<dyn CharAction>;
+
+/// What a command expects after its name. The text is the usage of the arguments, like
+/// `<item> [container]`.
+#[derive(Debug, Clone, Copy)]
+pub enum ArgSchema {
+    None,
+    Text(&'static str),
+    OptionalText(&'static str),
+}
+
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// The shortest prefix of the name which still selects the command.
+    pub min_abbrev: usize,
+    pub args: ArgSchema,
+    pub summary: &'static str,
+    pub handler: CommandHandler,
+}
+
+impl CommandSpec {
+    /// The name with its optional part in brackets, like `n[orth]`.
+    pub fn get_abbreviated_name(&self) -> String {
+        let min_abbrev = self.min_abbrev.clamp(1, self.name.len());
+
+        if min_abbrev == self.name.len() {
+            self.name.to_string()
+        } else {
+            format!("{}[{}]", &self.name[..min_abbrev], &self.name[min_abbrev..])
+        }
+    }
+
+    pub fn get_usage(&self) -> String {
+        match self.args {
+            ArgSchema::None => self.name.to_string(),
+            ArgSchema::Text(args) | ArgSchema::OptionalText(args) => {
+                format!("{} {}", self.name, args)
+            }
+        }
+    }
+
+    fn matches_prefix(&self, word: &str) -> bool {
+        word.len() >= self.min_abbrev.max(1) && self.name.starts_with(word)
+    }
+}
+
+pub enum Lookup<'a> {
+    Found(&'a CommandSpec),
+    Ambiguous(Vec<&'a CommandSpec>),
+    Unknown,
+}
+
+/// Every verb players can type. The order of registration is the order of the help.
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+
+        for spec in builtin_commands() {
+            if let Err(error) = registry.register(spec) {
+                eprintln!("Cannot register a builtin command: {error}");
+            }
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, spec: CommandSpec) -> Result<(), GameError> {
+        let taken = |word: &str| {
+            self.commands
+                .iter()
+                .any(|c| c.name == word || c.aliases.contains(&word))
+        };
+
+        if let Some(word) = std::iter::once(&spec.name)
+            .chain(spec.aliases.iter())
+            .find(|word| taken(word))
+        {
+            return Err(GameError::DuplicateCommand(word.to_string()));
+        }
+
+        self.commands.push(spec);
+
+        Ok(())
+    }
+
+    pub fn get_commands(&self) -> &[CommandSpec] {
+        &self.commands
+    }
+
+    /// Finds the command by its full name or alias first, then by a unique prefix of the name.
+    pub fn find(&self, word: &str) -> Lookup<'_> {
+        if let Some(spec) = self
+            .commands
+            .iter()
+            .find(|c| c.name == word || c.aliases.contains(&word))
+        {
+            return Lookup::Found(spec);
+        }
+
+        let mut candidates: Vec<&CommandSpec> = self
+            .commands
+            .iter()
+            .filter(|c| c.matches_prefix(word))
+            .collect();
+
+        match candidates.len() {
+            0 => Lookup::Unknown,
+            1 => Lookup::Found(candidates.remove(0)),
+            _ => Lookup::Ambiguous(candidates),
+        }
+    }
+
+    /// Turns a line of player input into the action to execute.
+    pub fn parse(&self, input: &str) -> Box<dyn CharAction> {
+        let (word, rest) = split_command(input);
+
+        if word.is_empty() {
+            return Box::new(Empty {});
+        }
+
+        let spec = match self.find(&word) {
+            Lookup::Found(spec) => spec,
+            Lookup::Unknown => return Box::new(UnknownCommand {}),
+            Lookup::Ambiguous(candidates) => {
+                let names: Vec<&str> = candidates.iter().map(|c| c.name).collect();
+                return Reply::boxed(format!("Did you mean: {}?", names.join(", ")));
+            }
+        };
+
+        let arguments_fit = match spec.args {
+            ArgSchema::None => rest.is_empty(),
+            ArgSchema::Text(_) => !rest.is_empty(),
+            ArgSchema::OptionalText(_) => true,
+        };
+        if !arguments_fit {
+            return Reply::boxed(format!("Usage: {}", spec.get_usage()));
+        }
+
+        (spec.handler)(self, rest)
+    }
+
+    pub fn render_usage(&self, name: &str) -> String {
+        match self.commands.iter().find(|c| c.name == name) {
+            None => format!("There is no command '{}'.", name),
+            Some(spec) => format!("Usage: {}", spec.get_usage()),
+        }
+    }
+
+    /// Lists every command, or describes the one the topic selects.
+    pub fn render_help(&self, topic: &str) -> String {
+        if topic.is_empty() {
+            let mut text = String::from("Commands:");
+            for spec in &self.commands {
+                text.push_str(&format!(
+                    "\n  {:<20} {}",
+                    spec.get_abbreviated_name(),
+                    spec.summary
+                ));
+            }
+            text.push_str("\nType 'help <command>' to learn more.");
+
+            return text;
+        }
+
+        match self.find(&topic.to_ascii_lowercase()) {
+            Lookup::Unknown => format!("There is no help on '{}'.", topic),
+            Lookup::Ambiguous(candidates) => {
+                let names: Vec<&str> = candidates.iter().map(|c| c.name).collect();
+                format!("Did you mean: {}?", names.join(", "))
+            }
+            Lookup::Found(spec) => {
+                let mut text = format!("Usage: {}", spec.get_usage());
+                let abbreviated_name = spec.get_abbreviated_name();
+                if abbreviated_name != spec.name {
+                    text.push_str(&format!("\nAbbreviation: {}", abbreviated_name));
+                }
+                if !spec.aliases.is_empty() {
+                    text.push_str(&format!("\nAliases: {}", spec.aliases.join(", ")));
+                }
+                text.push('\n');
+                text.push_str(spec.summary);
+
+                text
+            }
+        }
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits the arguments into the target word and the rest, skipping a leading preposition of the
+/// rest, so that `key from bag` and `key bag` both give `key` and `bag`.
+fn split_target(text: &str, prepositions: &[&str]) -> (Target, Option<Target>) {
+    let mut words = text.split_whitespace();
+    let target = Target::parse(words.next().unwrap_or(""));
+
+    let mut rest: Vec<&str> = words.collect();
+    if rest.len() > 1 && prepositions.iter().any(|p| p.eq_ignore_ascii_case(rest[0])) {
+        rest.remove(0);
+    }
+
+    if rest.is_empty() {
+        (target, None)
+    } else {
+        (target, Some(Target::parse(&rest.join(" "))))
+    }
+}
+
+/// Splits the input into the lowercase command word and the rest of the line with its spacing
+/// preserved. A leading `'` is a shortcut for `say`.
+fn split_command(input: &str) -> (String, &str) {
+    let input = input.trim();
+
+    if let Some(rest) = input.strip_prefix('\'') {
+        return (String::from("say"), rest.trim_start());
+    }
+
+    match input.split_once(char::is_whitespace) {
+        None => (input.to_ascii_lowercase(), ""),
+        Some((command, rest)) => (command.to_ascii_lowercase(), rest.trim_start()),
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Builtin commands
+// ----------------------------------------------------------------------------------------------------
+
+fn builtin_commands() -> Vec<CommandSpec> {
+    // The diagonals are longer than their cardinal prefixes, so they mostly go by the aliases.
+    vec![
+        CommandSpec {
+            name: "north",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Walk to the north.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::North)),
+        },
+        CommandSpec {
+            name: "south",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Walk to the south.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::South)),
+        },
+        CommandSpec {
+            name: "east",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Walk to the east.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::East)),
+        },
+        CommandSpec {
+            name: "west",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Walk to the west.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::West)),
+        },
+        CommandSpec {
+            name: "up",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Climb up.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::Up)),
+        },
+        CommandSpec {
+            name: "down",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "Climb down.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::Down)),
+        },
+        CommandSpec {
+            name: "northeast",
+            aliases: &["ne"],
+            min_abbrev: 6,
+            args: ArgSchema::None,
+            summary: "Walk to the northeast.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::NorthEast)),
+        },
+        CommandSpec {
+            name: "northwest",
+            aliases: &["nw"],
+            min_abbrev: 6,
+            args: ArgSchema::None,
+            summary: "Walk to the northwest.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::NorthWest)),
+        },
+        CommandSpec {
+            name: "southeast",
+            aliases: &["se"],
+            min_abbrev: 6,
+            args: ArgSchema::None,
+            summary: "Walk to the southeast.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::SouthEast)),
+        },
+        CommandSpec {
+            name: "southwest",
+            aliases: &["sw"],
+            min_abbrev: 6,
+            args: ArgSchema::None,
+            summary: "Walk to the southwest.",
+            handler: |_, _| Box::new(Move::new(MoveDirection::SouthWest)),
+        },
+        CommandSpec {
+            name: "look",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::OptionalText("[target]"),
+            summary: "Look around the room, or at someone or something here.",
+            handler: |_, target| {
+                let target = (!target.is_empty()).then(|| Target::parse(target));
+                Box::new(Look::new(target))
+            },
+        },
+        CommandSpec {
+            name: "open",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::Text("<door>"),
+            summary: "Open a door.",
+            handler: |_, door| Box::new(OperateDoor::new(DoorOperation::Open, door.to_string())),
+        },
+        CommandSpec {
+            name: "close",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<door>"),
+            summary: "Close a door.",
+            handler: |_, door| Box::new(OperateDoor::new(DoorOperation::Close, door.to_string())),
+        },
+        CommandSpec {
+            name: "lock",
+            aliases: &[],
+            min_abbrev: 3,
+            args: ArgSchema::Text("<door>"),
+            summary: "Lock a door. Some locks need a key.",
+            handler: |_, door| Box::new(OperateDoor::new(DoorOperation::Lock, door.to_string())),
+        },
+        CommandSpec {
+            name: "unlock",
+            aliases: &[],
+            min_abbrev: 3,
+            args: ArgSchema::Text("<door>"),
+            summary: "Unlock a door. Some locks need a key.",
+            handler: |_, door| Box::new(OperateDoor::new(DoorOperation::Unlock, door.to_string())),
+        },
+        CommandSpec {
+            name: "get",
+            aliases: &["take"],
+            min_abbrev: 1,
+            args: ArgSchema::Text("<item> [container]"),
+            summary: "Pick up an item, or take it out of a container. Use all or all.<item> for several.",
+            handler: |_, text| {
+                let (target, container) = split_target(text, &["from"]);
+                Box::new(GetItem::new(target, container))
+            },
+        },
+        CommandSpec {
+            name: "drop",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<item>"),
+            summary: "Drop an item you carry. Use all or all.<item> for several.",
+            handler: |_, text| Box::new(DropItem::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "put",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::Text("<item> <container>"),
+            summary: "Put an item you carry into a container. Use all or all.<item> for several.",
+            handler: |registry, text| match split_target(text, &["in", "into"]) {
+                (target, Some(container)) => Box::new(PutItem::new(target, container)),
+                (_, None) => Reply::boxed(registry.render_usage("put")),
+            },
+        },
+        CommandSpec {
+            name: "inventory",
+            aliases: &[],
+            min_abbrev: 1,
+            args: ArgSchema::None,
+            summary: "List what you carry.",
+            handler: |_, _| Box::new(Inventory {}),
+        },
+        CommandSpec {
+            name: "examine",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<item>"),
+            summary: "Look closely at an item here or in your inventory.",
+            handler: |_, text| Box::new(Examine::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "where",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<target>"),
+            summary: "Find out in which room of the world someone or something is.",
+            handler: |_, text| Box::new(Where::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "wear",
+            aliases: &[],
+            min_abbrev: 3,
+            args: ArgSchema::Text("<item>"),
+            summary: "Wear an item you carry.",
+            handler: |_, text| Box::new(Wear::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "wield",
+            aliases: &["hold"],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<item>"),
+            summary: "Take an item you carry in your hand.",
+            handler: |_, text| Box::new(Wield::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "remove",
+            aliases: &[],
+            min_abbrev: 3,
+            args: ArgSchema::Text("<item>"),
+            summary: "Stop wearing or wielding an item.",
+            handler: |_, text| Box::new(Remove::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "equipment",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::None,
+            summary: "List what you wear and wield.",
+            handler: |_, _| Box::new(Equipment {}),
+        },
+        CommandSpec {
+            name: "score",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::None,
+            summary: "Show your vitals and stats.",
+            handler: |_, _| Box::new(Score {}),
+        },
+        CommandSpec {
+            name: "kill",
+            aliases: &["attack"],
+            min_abbrev: 1,
+            args: ArgSchema::Text("<character>"),
+            summary: "Start a fight with someone in the room.",
+            handler: |_, text| Box::new(Kill::new(Target::parse(text))),
+        },
+        CommandSpec {
+            name: "flee",
+            aliases: &[],
+            min_abbrev: 2,
+            args: ArgSchema::None,
+            summary: "Run away from a fight through a random open exit.",
+            handler: |_, _| Box::new(Flee {}),
+        },
+        CommandSpec {
+            name: "say",
+            aliases: &["'"],
+            min_abbrev: 2,
+            args: ArgSchema::Text("<message>"),
+            summary: "Say the message to everyone in the room.",
+            handler: |_, text| Box::new(Say::new(text.to_string())),
+        },
+        CommandSpec {
+            name: "help",
+            aliases: &["?"],
+            min_abbrev: 1,
+            args: ArgSchema::OptionalText("[command]"),
+            summary: "List the commands or explain one of them.",
+            handler: |registry, topic| Reply::boxed(registry.render_help(topic)),
+        },
+        // Nobody should leave the game by a typo, so quit has no short form.
+        CommandSpec {
+            name: "quit",
+            aliases: &[],
+            min_abbrev: 4,
+            args: ArgSchema::None,
+            summary: "Leave the game.",
+            handler: |_, _| Box::new(Quit {}),
+        },
+    ]
+}
+
+/// Tells the player a text prepared while parsing the command.
+struct Reply {
+    text: String,
+}
+
+impl Reply {
+    fn boxed(text: String) -> Box<dyn CharAction> {
+        Box::new(Reply { text })
+    }
+}
+
+impl CharAction for Reply {
+    fn execute(&self, game: &mut Game, subject_id: PlayerId) -> Result<(), GameError> {
+        game.send_to_player(subject_id, &self.text);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every abbreviation a command allows must select it, or the help lies about it.
+    #[test]
+    fn abbreviations_select_their_command() {
+        let registry = CommandRegistry::with_builtins();
+
+        for spec in registry.get_commands() {
+            for length in spec.min_abbrev.max(1)..=spec.name.len() {
+                let word = &spec.name[..length];
+                match registry.find(word) {
+                    Lookup::Found(found) => assert_eq!(found.name, spec.name, "'{}'", word),
+                    _ => panic!("'{}' does not select {}", word, spec.name),
+                }
+            }
+        }
+    }
+
+    fn test_spec(name: &'static str, min_abbrev: usize) -> CommandSpec {
+        CommandSpec {
+            name,
+            aliases: &[],
+            min_abbrev,
+            args: ArgSchema::None,
+            summary: "Test.",
+            handler: |_, _| Box::new(Empty {}),
+        }
+    }
+
+    #[test]
+    fn unique_prefix_selects_the_command() {
+        let registry = CommandRegistry::with_builtins();
+
+        assert!(matches!(registry.find("inv"), Lookup::Found(spec) if spec.name == "inventory"));
+        assert!(matches!(registry.find("take"), Lookup::Found(spec) if spec.name == "get"));
+        assert!(matches!(registry.find("xyzzy"), Lookup::Unknown));
+    }
+
+    #[test]
+    fn prefix_shorter_than_min_abbrev_is_unknown() {
+        let registry = CommandRegistry::with_builtins();
+
+        assert!(matches!(registry.find("qui"), Lookup::Unknown));
+        assert!(matches!(registry.find("quit"), Lookup::Found(spec) if spec.name == "quit"));
+    }
+
+    #[test]
+    fn shared_prefix_is_ambiguous_unless_exact() {
+        let mut registry = CommandRegistry::new();
+        registry.register(test_spec("look", 1)).unwrap();
+        registry.register(test_spec("lock", 1)).unwrap();
+
+        match registry.find("lo") {
+            Lookup::Ambiguous(candidates) => {
+                let names: Vec<&str> = candidates.iter().map(|c| c.name).collect();
+                assert_eq!(names, ["look", "lock"]);
+            }
+            _ => panic!("'lo' should be ambiguous"),
+        }
+        assert!(matches!(registry.find("look"), Lookup::Found(spec) if spec.name == "look"));
+        assert!(registry.register(test_spec("look", 2)).is_err());
+    }
+
+    #[test]
+    fn help_lists_and_explains_commands() {
+        let registry = CommandRegistry::with_builtins();
+
+        let list = registry.render_help("");
+        assert!(list.starts_with("Commands:"));
+        assert!(list.contains("n[orth]"));
+        assert!(list.contains("wea[r]"));
+
+        let wear = registry.render_help("wea");
+        assert!(wear.starts_with("Usage: wear <item>\nAbbreviation: wea[r]"));
+        assert_eq!(
+            registry.render_help("xyzzy"),
+            "There is no help on 'xyzzy'."
+        );
+    }
+
+    #[test]
+    fn we_walks_west() {
+        let registry = CommandRegistry::with_builtins();
+
+        assert!(matches!(registry.find("we"), Lookup::Found(spec) if spec.name == "west"));
+        assert!(matches!(registry.find("wea"), Lookup::Found(spec) if spec.name == "wear"));
+    }
+}
@@ -0,0 +1,573 @@
+use crate::account::{normalize_name, send_name_rules};
+use crate::game::{Game, PlayerId};
+use crate::item::capitalize;
+use crate::socium::{BASE_ATTRIBUTE, Character, CharacterId, Stat, Vital, VitalPool};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// How far a rolled attribute may land from the base one, either way.
+const ATTRIBUTE_ROLL_SPREAD: i32 = 3;
+
+/// Everything a new character is made of, as designers describe it in the creation file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreationRules {
+    pub races: Vec<Race>,
+    pub classes: Vec<Class>,
+    /// Names nobody may take, whatever the case.
+    #[serde(default)]
+    pub reserved_names: Vec<String>,
+    /// Words no name may contain.
+    #[serde(default)]
+    pub banned_words: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Race {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// What the race adds to the rolled attributes.
+    #[serde(default)]
+    pub stats: BTreeMap<Stat, i32>,
+    /// What the race adds to the maximums of the vitals.
+    #[serde(default)]
+    pub vitals: BTreeMap<Vital, i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Class {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub stats: BTreeMap<Stat, i32>,
+    #[serde(default)]
+    pub vitals: BTreeMap<Vital, i32>,
+}
+
+impl CreationRules {
+    /// Finds every problem which would make the creation fail or be ambiguous.
+    pub fn validate(&self) -> Vec<CreationError> {
+        let mut problems = Vec::new();
+
+        if self.races.is_empty() {
+            problems.push(CreationError::NoRaces);
+        }
+        if self.classes.is_empty() {
+            problems.push(CreationError::NoClasses);
+        }
+
+        let mut race_names = HashSet::new();
+        for race in &self.races {
+            if !race_names.insert(race.name.to_lowercase()) {
+                problems.push(CreationError::DuplicateRace(race.name.clone()));
+            }
+        }
+        let mut class_names = HashSet::new();
+        for class in &self.classes {
+            if !class_names.insert(class.name.to_lowercase()) {
+                problems.push(CreationError::DuplicateClass(class.name.clone()));
+            }
+        }
+
+        problems
+    }
+
+    /// Whether the name is reserved or contains a banned word.
+    pub fn is_name_forbidden(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+
+        self.reserved_names
+            .iter()
+            .any(|reserved| reserved.to_lowercase() == name)
+            || self
+                .banned_words
+                .iter()
+                .any(|word| name.contains(&word.to_lowercase()))
+    }
+}
+
+/// A character the wizard is done with, waiting for an id in the world.
+#[derive(Debug, Clone)]
+pub struct CharacterDraft {
+    pub name: String,
+    pub race: Race,
+    pub class: Class,
+    /// The rolled attributes with the race and class added.
+    pub stats: BTreeMap<Stat, i32>,
+}
+
+impl CharacterDraft {
+    pub fn build(&self, id: CharacterId) -> Character {
+        let mut char = Character::new(id);
+        char.set_name(self.name.clone());
+        char.set_race(self.race.name.clone());
+        char.set_class(self.class.name.clone());
+
+        for (stat, value) in &self.stats {
+            char.set_base_stat(*stat, *value);
+        }
+        for vital in Vital::ALL {
+            let bonus = self.race.vitals.get(&vital).copied().unwrap_or(0)
+                + self.class.vitals.get(&vital).copied().unwrap_or(0);
+            *char.get_vital_mut(vital) = VitalPool::new((vital.get_base_max() + bonus).max(1));
+        }
+
+        char
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Wizard
+// ----------------------------------------------------------------------------------------------------
+
+enum WizardStep {
+    Name,
+    Race { name: String },
+    Class { name: String, race: Race },
+    Attributes { draft: CharacterDraft },
+}
+
+/// Asks a player the name, the race and the class of a new character, then rolls the attributes
+/// until they keep them.
+pub struct CharacterWizard {
+    rules: Rc<CreationRules>,
+    step: WizardStep,
+}
+
+impl CharacterWizard {
+    pub fn new(rules: Rc<CreationRules>) -> Self {
+        Self {
+            rules,
+            step: WizardStep::Name,
+        }
+    }
+
+    pub fn start(&self, game: &mut Game, player_id: PlayerId) {
+        game.send_to_player(player_id, "What is the name of your new character?");
+    }
+
+    /// Takes the answer to the last question. `is_name_taken` tells whether someone else goes by
+    /// the name already. Returns the character once the player keeps the attributes.
+    pub fn handle(
+        &mut self,
+        game: &mut Game,
+        player_id: PlayerId,
+        is_name_taken: &dyn Fn(&str) -> bool,
+        input: &str,
+    ) -> Option<CharacterDraft> {
+        let step = std::mem::replace(&mut self.step, WizardStep::Name);
+
+        let (step, draft) = match step {
+            WizardStep::Name => (
+                self.choose_name(game, player_id, is_name_taken, input),
+                None,
+            ),
+            WizardStep::Race { name } => (self.choose_race(game, player_id, name, input), None),
+            WizardStep::Class { name, race } => {
+                (self.choose_class(game, player_id, name, race, input), None)
+            }
+            WizardStep::Attributes { draft } => match input.to_lowercase().as_str() {
+                "keep" | "k" => (
+                    WizardStep::Attributes {
+                        draft: draft.clone(),
+                    },
+                    Some(draft),
+                ),
+                "reroll" | "r" => {
+                    let draft = roll(draft.name, draft.race, draft.class);
+                    send_attributes(game, player_id, &draft);
+                    (WizardStep::Attributes { draft }, None)
+                }
+                _ => {
+                    send_attributes(game, player_id, &draft);
+                    (WizardStep::Attributes { draft }, None)
+                }
+            },
+        };
+
+        self.step = step;
+        draft
+    }
+
+    fn choose_name(
+        &self,
+        game: &mut Game,
+        player_id: PlayerId,
+        is_name_taken: &dyn Fn(&str) -> bool,
+        input: &str,
+    ) -> WizardStep {
+        let Some(name) = normalize_name(input) else {
+            send_name_rules(game, player_id);
+            return WizardStep::Name;
+        };
+        if self.rules.is_name_forbidden(&name) {
+            game.send_to_player(player_id, "That name is not allowed. Choose another one:");
+            return WizardStep::Name;
+        }
+        if is_name_taken(&name) {
+            let text = format!("Someone goes by {} already. Choose another name:", name);
+            game.send_to_player(player_id, &text);
+            return WizardStep::Name;
+        }
+
+        send_races(game, player_id, &self.rules);
+        WizardStep::Race { name }
+    }
+
+    fn choose_race(
+        &self,
+        game: &mut Game,
+        player_id: PlayerId,
+        name: String,
+        input: &str,
+    ) -> WizardStep {
+        let Some(race) = find_choice(&self.rules.races, input, |race| &race.name) else {
+            send_races(game, player_id, &self.rules);
+            return WizardStep::Race { name };
+        };
+
+        send_classes(game, player_id, &self.rules);
+        WizardStep::Class {
+            name,
+            race: race.clone(),
+        }
+    }
+
+    fn choose_class(
+        &self,
+        game: &mut Game,
+        player_id: PlayerId,
+        name: String,
+        race: Race,
+        input: &str,
+    ) -> WizardStep {
+        let Some(class) = find_choice(&self.rules.classes, input, |class| &class.name) else {
+            send_classes(game, player_id, &self.rules);
+            return WizardStep::Class { name, race };
+        };
+
+        let draft = roll(name, race, class.clone());
+        send_attributes(game, player_id, &draft);
+        WizardStep::Attributes { draft }
+    }
+}
+
+/// Picks an option by its number in the list or by its name.
+fn find_choice<'a, T>(
+    options: &'a [T],
+    input: &str,
+    name_of: impl Fn(&T) -> &str,
+) -> Option<&'a T> {
+    match input.trim().parse::<usize>() {
+        Ok(number) => number.checked_sub(1).and_then(|i| options.get(i)),
+        Err(_) => options
+            .iter()
+            .find(|option| name_of(option).eq_ignore_ascii_case(input.trim())),
+    }
+}
+
+fn roll(name: String, race: Race, class: Class) -> CharacterDraft {
+    let stats = Stat::ATTRIBUTES
+        .iter()
+        .map(|stat| {
+            let rolled = BASE_ATTRIBUTE
+                + rand::random_range(-ATTRIBUTE_ROLL_SPREAD..=ATTRIBUTE_ROLL_SPREAD)
+                + race.stats.get(stat).copied().unwrap_or(0)
+                + class.stats.get(stat).copied().unwrap_or(0);
+            (*stat, rolled.max(1))
+        })
+        .collect();
+
+    CharacterDraft {
+        name,
+        race,
+        class,
+        stats,
+    }
+}
+
+fn send_races(game: &mut Game, player_id: PlayerId, rules: &CreationRules) {
+    let mut text = String::from("Choose a race:");
+    for (i, race) in rules.races.iter().enumerate() {
+        text.push_str(&format!(
+            "\n  {}. {:<10}{}",
+            i + 1,
+            race.name,
+            race.description
+        ));
+    }
+
+    game.send_to_player(player_id, &text);
+}
+
+fn send_classes(game: &mut Game, player_id: PlayerId, rules: &CreationRules) {
+    let mut text = String::from("Choose a class:");
+    for (i, class) in rules.classes.iter().enumerate() {
+        text.push_str(&format!(
+            "\n  {}. {:<10}{}",
+            i + 1,
+            class.name,
+            class.description
+        ));
+    }
+
+    game.send_to_player(player_id, &text);
+}
+
+fn send_attributes(game: &mut Game, player_id: PlayerId, draft: &CharacterDraft) {
+    let mut text = format!(
+        "{} the {} {} has rolled:",
+        draft.name, draft.race.name, draft.class.name
+    );
+    for (stat, value) in &draft.stats {
+        text.push_str(&format!("\n  {:<14}{}", capitalize(stat.get_name()), value));
+    }
+    text.push_str("\nType 'keep' to play with these or 'reroll' to roll again.");
+
+    game.send_to_player(player_id, &text);
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Loading
+// ----------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum CreationError {
+    NoRaces,
+    NoClasses,
+    DuplicateRace(String),
+    DuplicateClass(String),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationError::NoRaces => write!(f, "there are no races"),
+            CreationError::NoClasses => write!(f, "there are no classes"),
+            CreationError::DuplicateRace(name) => {
+                write!(f, "race '{}' is defined more than once", name)
+            }
+            CreationError::DuplicateClass(name) => {
+                write!(f, "class '{}' is defined more than once", name)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CreationLoadError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: Box<ron::error::SpannedError>,
+    },
+    Invalid {
+        path: PathBuf,
+        problems: Vec<CreationError>,
+    },
+}
+
+impl fmt::Display for CreationLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreationLoadError::Io { path, error } => {
+                write!(f, "cannot read {}: {}", path.display(), error)
+            }
+            CreationLoadError::Parse { path, error } => {
+                write!(f, "cannot parse {}: {}", path.display(), error)
+            }
+            CreationLoadError::Invalid { path, problems } => {
+                write!(f, "invalid creation rules in {}:", path.display())?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for CreationLoadError {}
+
+/// Reads and validates the races, the classes and the name rules.
+pub fn load_rules_file(path: &Path) -> Result<CreationRules, CreationLoadError> {
+    let text = fs::read_to_string(path).map_err(|error| CreationLoadError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let rules: CreationRules = ron::from_str(&text).map_err(|error| CreationLoadError::Parse {
+        path: path.to_path_buf(),
+        error: Box::new(error),
+    })?;
+
+    let problems = rules.validate();
+    if !problems.is_empty() {
+        return Err(CreationLoadError::Invalid {
+            path: path.to_path_buf(),
+            problems,
+        });
+    }
+
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"(
+        races: [
+            (name: "Human"),
+            (name: "Elf", stats: {Dexterity: 2, Strength: -20}, vitals: {Mana: 10, HitPoints: -50}),
+        ],
+        classes: [
+            (name: "Warrior", stats: {Strength: 2}, vitals: {HitPoints: 10}),
+            (name: "Mage", stats: {Intelligence: 3}, vitals: {Mana: 20}),
+        ],
+        reserved_names: ["Admin"],
+        banned_words: ["darn"],
+    )"#;
+
+    fn wizard() -> CharacterWizard {
+        CharacterWizard::new(Rc::new(ron::from_str(RULES).unwrap()))
+    }
+
+    /// Feeds the answers to the wizard, with `Bob` as the only taken name.
+    fn answer(
+        game: &mut Game,
+        wizard: &mut CharacterWizard,
+        inputs: &[&str],
+    ) -> Option<CharacterDraft> {
+        let is_name_taken = |name: &str| name == "Bob";
+
+        inputs.iter().fold(None, |_, input| {
+            wizard.handle(game, PlayerId::from(1), &is_name_taken, input)
+        })
+    }
+
+    #[test]
+    fn invalid_forbidden_and_taken_names_are_asked_again() {
+        let mut game = Game::new();
+        let mut wizard = wizard();
+
+        for name in ["x", "R2d2", "admin", "Darnell", "bob"] {
+            answer(&mut game, &mut wizard, &[name]);
+            assert!(matches!(wizard.step, WizardStep::Name), "{name}");
+        }
+        let output = game.take_output(PlayerId::from(1)).unwrap();
+        assert!(output.contains("A name is one word"));
+        assert!(output.contains("That name is not allowed."));
+        assert!(output.contains("Someone goes by Bob already."));
+
+        answer(&mut game, &mut wizard, &["alice"]);
+        assert!(matches!(&wizard.step, WizardStep::Race { name } if name == "Alice"));
+    }
+
+    #[test]
+    fn race_and_class_are_chosen_by_number_or_name() {
+        let mut game = Game::new();
+        let mut wizard = wizard();
+
+        answer(&mut game, &mut wizard, &["Alice", "dwarf"]);
+        assert!(matches!(wizard.step, WizardStep::Race { .. }));
+
+        answer(&mut game, &mut wizard, &["2", "0"]);
+        assert!(matches!(&wizard.step, WizardStep::Class { race, .. } if race.name == "Elf"));
+
+        answer(&mut game, &mut wizard, &["MAGE"]);
+        let WizardStep::Attributes { draft } = &wizard.step else {
+            panic!("the attributes are not rolled");
+        };
+        assert_eq!(draft.name, "Alice");
+        assert_eq!(draft.race.name, "Elf");
+        assert_eq!(draft.class.name, "Mage");
+    }
+
+    #[test]
+    fn attributes_are_rerolled_until_kept() {
+        let mut game = Game::new();
+        let mut wizard = wizard();
+
+        let draft = answer(
+            &mut game,
+            &mut wizard,
+            &["Alice", "1", "1", "reroll", "r", "what"],
+        );
+        assert!(draft.is_none());
+        let output = game.take_output(PlayerId::from(1)).unwrap();
+        assert_eq!(
+            output
+                .matches("Alice the Human Warrior has rolled:")
+                .count(),
+            4
+        );
+
+        let draft = answer(&mut game, &mut wizard, &["keep"]).unwrap();
+        assert_eq!(draft.name, "Alice");
+        for stat in Stat::ATTRIBUTES {
+            let bonus = if stat == Stat::Strength { 2 } else { 0 };
+            let spread = (BASE_ATTRIBUTE + bonus - ATTRIBUTE_ROLL_SPREAD)
+                ..=(BASE_ATTRIBUTE + bonus + ATTRIBUTE_ROLL_SPREAD);
+            assert!(spread.contains(&draft.stats[&stat]), "{stat:?}");
+        }
+    }
+
+    #[test]
+    fn draft_builds_a_character_with_the_race_and_class_bonuses() {
+        let mut game = Game::new();
+        let mut wizard = wizard();
+        let draft = answer(&mut game, &mut wizard, &["Alice", "elf", "warrior", "keep"]).unwrap();
+
+        // The frail elf gets no less than one of anything.
+        assert_eq!(draft.stats[&Stat::Strength], 1);
+
+        let char = draft.build(CharacterId::from(5));
+        assert_eq!(char.get_id(), CharacterId::from(5));
+        assert_eq!(char.get_name(), "Alice");
+        assert_eq!(char.get_race(), Some("Elf"));
+        assert_eq!(char.get_class(), Some("Warrior"));
+        for (stat, value) in &draft.stats {
+            assert_eq!(char.get_base_stats().get(*stat), *value);
+        }
+        assert_eq!(char.get_vital(Vital::HitPoints).get_max(), 1);
+        assert_eq!(
+            char.get_vital(Vital::Mana).get_max(),
+            Vital::Mana.get_base_max() + 10
+        );
+        assert_eq!(
+            char.get_vital(Vital::Movement).get_max(),
+            Vital::Movement.get_base_max()
+        );
+    }
+
+    #[test]
+    fn duplicate_and_missing_choices_are_invalid() {
+        let rules: CreationRules =
+            ron::from_str(r#"(races: [(name: "Elf"), (name: "elf")], classes: [])"#).unwrap();
+
+        let problems: Vec<String> = rules.validate().iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            [
+                "there are no classes",
+                "race 'elf' is defined more than once"
+            ]
+        );
+        assert!(
+            ron::from_str::<CreationRules>(RULES)
+                .unwrap()
+                .validate()
+                .is_empty()
+        );
+    }
+}
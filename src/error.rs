@@ -0,0 +1,68 @@
+use crate::area::{ExitKey, RoomId, WorldId};
+use crate::game::PlayerId;
+use crate::item::{ItemId, WearSlot};
+use crate::socium::CharacterId;
+use std::error::Error;
+use std::fmt;
+
+/// Failures of the game API. None of them is fatal to the process: the caller decides whether to
+/// tell a player, skip an entity or refuse to start.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GameError {
+    DuplicateWorld(WorldId),
+    DuplicatePlayer(PlayerId),
+    DuplicateCharacter(CharacterId),
+    DuplicateCommand(String),
+    DuplicateItem(ItemId),
+    DuplicateNpcTemplate(u32),
+    UnknownWorld(WorldId),
+    UnknownPlayer(PlayerId),
+    UnknownCharacter(CharacterId),
+    UnknownRoom(RoomId),
+    UnknownItem(ItemId),
+    UnknownNpcTemplate(u32),
+    ItemInsideItself(ItemId),
+    CannotEquip { item: ItemId, slot: WearSlot },
+    EmptyWorld(WorldId),
+    DanglingExit { room: RoomId, exit: ExitKey },
+    ExitTaken { room: RoomId, exit: ExitKey },
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::DuplicateWorld(id) => write!(f, "world {:?} already exists", id),
+            GameError::DuplicatePlayer(id) => write!(f, "player {:?} already exists", id),
+            GameError::DuplicateCharacter(id) => write!(f, "character {} already exists", id),
+            GameError::DuplicateCommand(word) => write!(f, "command '{}' already exists", word),
+            GameError::DuplicateItem(id) => write!(f, "item {} already exists", id),
+            GameError::DuplicateNpcTemplate(id) => write!(f, "NPC template {} already exists", id),
+            GameError::UnknownWorld(id) => write!(f, "no world {:?}", id),
+            GameError::UnknownPlayer(id) => write!(f, "no player {:?}", id),
+            GameError::UnknownCharacter(id) => write!(f, "no character {}", id),
+            GameError::UnknownRoom(id) => write!(f, "no room {}", id),
+            GameError::UnknownItem(id) => write!(f, "no item {}", id),
+            GameError::UnknownNpcTemplate(id) => write!(f, "no NPC template {}", id),
+            GameError::ItemInsideItself(id) => write!(f, "item {} cannot go inside itself", id),
+            GameError::CannotEquip { item, slot } => {
+                write!(
+                    f,
+                    "item {} cannot be equipped on the {}",
+                    item,
+                    slot.get_name()
+                )
+            }
+            GameError::EmptyWorld(id) => write!(f, "world {:?} has no rooms", id),
+            GameError::DanglingExit { room, exit } => write!(
+                f,
+                "exit {} of room {} leads to a room which is gone",
+                exit, room
+            ),
+            GameError::ExitTaken { room, exit } => {
+                write!(f, "exit {} of room {} already leads elsewhere", exit, room)
+            }
+        }
+    }
+}
+
+impl Error for GameError {}
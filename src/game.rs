@@ -1,17 +1,36 @@
-use crate::action::GameAction;
-use crate::area::{MoveDirection, Room, RoomId, World, WorldId, WorldRef};
+use crate::action::{CharAction, GameAction, Move};
+use crate::area::{Room, World, WorldId, WorldRef};
+use crate::command::CommandRegistry;
+use crate::error::GameError;
+use crate::item::ItemId;
+use crate::output::Output;
+use crate::script::{self, ScriptEngine, ScriptEvent, ScriptTrigger};
 use crate::socium::{Character, CharacterId, CharacterRef};
-use crate::utils::{none_or_panic, BuildRef};
-use derive_more::From;
+use crate::utils::BuildRef;
+use derive_more::{From, Into};
 use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What the tick scripts of all the rooms may take together, so that they cannot stall a pulse.
+const TICK_SCRIPTS_MAX_DURATION: Duration = Duration::from_millis(50);
+const TICK_SCRIPTS_MAX_OPERATIONS: u64 = 500_000;
 
 pub struct Game {
     worlds: HashMap<WorldId, WorldRef>,
     players: HashMap<PlayerId, PlayerRef>,
+    next_player_id: u32,
     actions_queue: VecDeque<GameAction>,
+    clock: GameClock,
+    scheduler: Scheduler,
+    output: Output,
+    ended_sessions: Vec<PlayerId>,
+    commands: Rc<CommandRegistry>,
+    scripts: ScriptEngine,
+    events: EventBus,
 }
 
 impl Game {
@@ -19,54 +38,266 @@ impl Game {
         Self {
             worlds: HashMap::new(),
             players: HashMap::new(),
+            next_player_id: 1,
             actions_queue: VecDeque::new(),
+            clock: GameClock::new(DEFAULT_PULSES_PER_SECOND),
+            scheduler: Scheduler::new(),
+            output: Output::new(),
+            ended_sessions: Vec::new(),
+            commands: Rc::new(CommandRegistry::with_builtins()),
+            scripts: ScriptEngine::new(),
+            events: EventBus::with_builtins(),
+        }
+    }
+
+    /// The registry is shared so that a parsed command can run against the game mutably.
+    pub fn get_commands(&self) -> Rc<CommandRegistry> {
+        self.commands.clone()
+    }
+
+    pub fn set_commands(&mut self, commands: CommandRegistry) {
+        self.commands = Rc::new(commands);
+    }
+
+    /// Turns a line the player typed into an action. The exit keywords of the room the player
+    /// stands in come before the commands, so that a room can offer its own `out` or `climb`.
+    pub fn parse_command(&self, player_id: PlayerId, input: &str) -> Box<dyn CharAction> {
+        let room = self
+            .get_player_char(player_id)
+            .and_then(|char| char.borrow().get_current_room().clone());
+
+        if let Some(exit) = room.and_then(|room| room.find_exit_keyword(input)) {
+            return Box::new(Move::new(exit));
+        }
+
+        self.commands.parse(input)
+    }
+
+    /// Tells the subscribers about the event once the current actions are resolved.
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.publish(event);
+    }
+
+    pub fn subscribe(&mut self, subscriber: Subscriber) {
+        self.events.subscribe(subscriber);
+    }
+
+    pub fn get_scripts_mut(&mut self) -> &mut ScriptEngine {
+        &mut self.scripts
+    }
+
+    pub fn get_output_mut(&mut self) -> &mut Output {
+        &mut self.output
+    }
+
+    pub fn send_to_player(&mut self, player_id: PlayerId, text: &str) {
+        self.output.send(player_id, text);
+    }
+
+    /// Sends the text to every player whose character stands in the room.
+    pub fn send_to_room(&mut self, room: &Room, except: Option<PlayerId>, text: &str) {
+        let recipients = room.get_world().borrow().get_players_in_room(room);
+
+        for player_id in recipients {
+            if Some(player_id) != except {
+                self.output.send(player_id, text);
+            }
+        }
+    }
+
+    /// Sends the text to every player whose character is in the world.
+    pub fn send_to_world(&mut self, world_id: WorldId, text: &str) {
+        let recipients: Vec<PlayerId> = self
+            .players
+            .values()
+            .filter(|p| match p.borrow().get_main_char() {
+                None => false,
+                Some(char) => match char.borrow().get_current_room() {
+                    None => false,
+                    Some(r) => r.get_world().borrow().get_id() == world_id,
+                },
+            })
+            .map(|p| p.borrow().get_id())
+            .collect();
+
+        for player_id in recipients {
+            self.output.send(player_id, text);
+        }
+    }
+
+    /// Takes everything sent to the player since the last call.
+    pub fn take_output(&mut self, player_id: PlayerId) -> Option<String> {
+        self.output.take(player_id)
+    }
+
+    pub fn get_clock(&self) -> &GameClock {
+        &self.clock
+    }
+
+    pub fn get_clock_mut(&mut self) -> &mut GameClock {
+        &mut self.clock
+    }
+
+    /// Runs one pulse of the world: resolves queued actions, fires due scheduled events and
+    /// tells the subscribers what happened. The output waits in the buffers for the transport.
+    pub fn tick(&mut self) {
+        self.clock.advance();
+
+        self.process_actions_queue();
+        self.fire_scheduled_events();
+        self.dispatch_events();
+    }
+
+    /// Lets every character of every world who is not fighting recover a part of their vitals.
+    pub fn regenerate_characters(&mut self) {
+        for world in self.worlds.values() {
+            for char in world.borrow().get_characters() {
+                if !char.borrow().is_fighting() {
+                    char.borrow_mut().regenerate();
+                }
+            }
+        }
+    }
+
+    /// Queues a blow of every fighting character at their opponent.
+    pub fn queue_combat_rounds(&mut self) {
+        let mut attacks = Vec::new();
+
+        for world in self.worlds.values() {
+            let world = world.borrow();
+
+            for char in world.get_characters() {
+                let Some(opponent_id) = char.borrow().get_fighting() else {
+                    continue;
+                };
+
+                match world.get_character(opponent_id) {
+                    Some(victim) => attacks.push(GameAction::Attack {
+                        attacker: char.clone(),
+                        victim: victim.clone(),
+                    }),
+                    None => char.borrow_mut().unset_fighting(),
+                }
+            }
+        }
+
+        for attack in attacks {
+            self.queue_action(attack);
         }
     }
 
-    pub fn add_world(&mut self, world: World) -> WorldId {
+    /// Lets every NPC act on its behaviors.
+    pub fn run_behaviors(&mut self) {
+        let actions: Vec<GameAction> = self
+            .worlds
+            .values()
+            .flat_map(|world| world.borrow().run_behaviors())
+            .collect();
+
+        for action in actions {
+            self.queue_action(action);
+        }
+    }
+
+    /// Runs the tick scripts of every room, with the NPCs and items in it.
+    pub fn run_tick_scripts(&mut self) {
+        let rooms: Vec<Rc<Room>> = self
+            .worlds
+            .values()
+            .flat_map(|world| world.borrow().get_rooms().to_vec())
+            .collect();
+
+        self.scripts
+            .set_budget(TICK_SCRIPTS_MAX_DURATION, TICK_SCRIPTS_MAX_OPERATIONS);
+        for room in rooms {
+            script::fire_event(self, ScriptEvent::new(ScriptTrigger::Tick, room));
+        }
+        self.scripts.unset_budget();
+    }
+
+    pub fn add_world(&mut self, world: World) -> Result<WorldId, GameError> {
         let id = world.get_id();
-        none_or_panic(
-            self.worlds.insert(id, world.build_ref()),
-            "World already exists",
-        );
+        if self.worlds.contains_key(&id) {
+            return Err(GameError::DuplicateWorld(id));
+        }
+        self.worlds.insert(id, world.build_ref());
 
-        id
+        Ok(id)
     }
 
     pub fn get_world(&self, id: WorldId) -> Option<&WorldRef> {
         self.worlds.get(&id)
     }
 
-    pub fn add_player(&mut self, player: Player) -> PlayerId {
+    pub fn require_world(&self, id: WorldId) -> Result<&WorldRef, GameError> {
+        self.get_world(id).ok_or(GameError::UnknownWorld(id))
+    }
+
+    pub fn get_worlds(&self) -> impl Iterator<Item = &WorldRef> {
+        self.worlds.values()
+    }
+
+    pub fn add_player(&mut self, player: Player) -> Result<PlayerId, GameError> {
         let id = player.get_id();
-        none_or_panic(
-            self.players.insert(id, player.build_ref()),
-            "Player already exists",
-        );
+        if self.players.contains_key(&id) {
+            return Err(GameError::DuplicatePlayer(id));
+        }
+        self.players.insert(id, player.build_ref());
+
+        self.next_player_id = self.next_player_id.max(id.0 + 1);
+
+        Ok(id)
+    }
+
+    /// Returns an id which is not taken by any player yet.
+    pub fn allocate_player_id(&mut self) -> PlayerId {
+        let id = PlayerId::from(self.next_player_id);
+        self.next_player_id += 1;
 
         id
     }
 
+    /// Removes the player from the game and takes their character out of its world.
+    pub fn disconnect_player(&mut self, player_id: PlayerId) {
+        let Some(player) = self.get_player(player_id) else {
+            return;
+        };
+
+        let main_char = player.borrow().get_main_char().clone();
+
+        if let Some(char) = main_char {
+            player.borrow_mut().unset_main_char();
+            char.borrow_mut().unset_owner();
+
+            let char_id = char.borrow().get_id();
+            let char_name = char.borrow().get_name();
+            let room = char.borrow().get_current_room().clone();
+
+            if let Some(room) = room {
+                room.get_world().borrow_mut().despawn_character(char_id);
+                self.send_to_room(&room, None, &format!("{} vanishes.", char_name));
+            }
+        }
+
+        self.players.remove(&player_id);
+        self.output.discard(player_id);
+    }
+
     pub fn get_player(&self, id: PlayerId) -> Option<&PlayerRef> {
         self.players.get(&id)
     }
 
-    pub fn fill_world(&mut self, world_id: WorldId) {
-        let world = self.get_world(world_id).unwrap();
-        let room1 = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
-        let room2 = Rc::new(Room::new_in_void(RoomId::from(2), world.clone()));
-
-        room1.set_exit(&MoveDirection::North, room2.clone());
-        room1.set_exit(&MoveDirection::South, room2.clone());
-        room1.set_exit(&MoveDirection::East, room1.clone());
+    pub fn require_player(&self, id: PlayerId) -> Result<&PlayerRef, GameError> {
+        self.get_player(id).ok_or(GameError::UnknownPlayer(id))
+    }
 
-        room2.set_exit(&MoveDirection::North, room1.clone());
-        room2.set_exit(&MoveDirection::South, room1.clone());
-        room2.set_exit(&MoveDirection::West, room2.clone());
+    pub fn get_players(&self) -> impl Iterator<Item = &PlayerRef> {
+        self.players.values()
+    }
 
-        let mut world_mut = world.borrow_mut();
-        world_mut.add_room(room1);
-        world_mut.add_room(room2);
+    /// Returns the character the player currently controls.
+    pub fn get_player_char(&self, id: PlayerId) -> Option<CharacterRef> {
+        self.get_player(id)?.borrow().get_main_char().clone()
     }
 
     pub fn spawn_player_character(
@@ -74,11 +305,29 @@ impl Game {
         world_id: WorldId,
         player_id: PlayerId,
         character: Character,
-    ) {
-        let world = self.get_world(world_id).unwrap();
-        let char_id = world.borrow_mut().spawn_character(character);
+    ) -> Result<CharacterId, GameError> {
+        self.require_player(player_id)?;
+
+        let world = self.require_world(world_id)?.clone();
+        let char_name = character.get_name();
+        let char_id = world.borrow_mut().spawn_character(character)?;
+
+        self.set_player_character(world_id, player_id, char_id)?;
+
+        let room = world
+            .borrow()
+            .get_character(char_id)
+            .and_then(|char| char.borrow().get_current_room().clone());
+
+        if let Some(room) = room {
+            self.send_to_room(&room, Some(player_id), &format!("{} appears.", char_name));
+
+            if let Some(char) = self.get_player_char(player_id) {
+                self.publish(GameEvent::CharacterSpawned { who: char, room });
+            }
+        }
 
-        self.set_player_character(world_id, player_id, char_id);
+        Ok(char_id)
     }
 
     pub fn set_player_character(
@@ -86,18 +335,22 @@ impl Game {
         world_id: WorldId,
         player_id: PlayerId,
         char_id: CharacterId,
-    ) {
-        let world = self.get_world(world_id).unwrap();
+    ) -> Result<(), GameError> {
+        let world = self.require_world(world_id)?;
 
         let world_ref = world.borrow();
-        let char = world_ref.get_character(char_id).unwrap();
+        let char = world_ref
+            .get_character(char_id)
+            .ok_or(GameError::UnknownCharacter(char_id))?;
         let char_ref = char.clone();
 
-        let player = self.get_player(player_id).unwrap();
+        let player = self.require_player(player_id)?;
         let player_ref = player.clone();
 
         player.borrow_mut().set_main_char(char_ref);
         char.borrow_mut().set_owner(player_ref);
+
+        Ok(())
     }
 
     pub fn unset_player_character(
@@ -105,15 +358,30 @@ impl Game {
         world_id: WorldId,
         player_id: PlayerId,
         char_id: CharacterId,
-    ) {
-        let world = self.get_world(world_id).unwrap();
+    ) -> Result<(), GameError> {
+        let world = self.require_world(world_id)?;
 
         let world_ref = world.borrow();
-        let char = world_ref.get_character(char_id).unwrap();
-        let player = self.get_player(player_id).unwrap();
+        let char = world_ref
+            .get_character(char_id)
+            .ok_or(GameError::UnknownCharacter(char_id))?;
+        let player = self.require_player(player_id)?;
 
         player.borrow_mut().unset_main_char();
         char.borrow_mut().unset_owner();
+
+        Ok(())
+    }
+
+    /// Asks the transport to close the player's connection once their output is sent.
+    pub fn end_session(&mut self, player_id: PlayerId) {
+        if !self.ended_sessions.contains(&player_id) {
+            self.ended_sessions.push(player_id);
+        }
+    }
+
+    pub fn take_ended_sessions(&mut self) -> Vec<PlayerId> {
+        std::mem::take(&mut self.ended_sessions)
     }
 
     pub fn queue_action(&mut self, action: GameAction) {
@@ -121,18 +389,355 @@ impl Game {
     }
 
     pub fn process_actions_queue(&mut self) {
-        //for action in self.actions_queue.drain(..) {}
+        // Actions queued while resolving wait for the next call.
+        let actions: Vec<GameAction> = self.actions_queue.drain(..).collect();
+
+        for action in actions {
+            action.resolve(self);
+        }
+    }
+
+    /// Queues the action to be resolved after `delay` ticks.
+    pub fn schedule_action(&mut self, delay: Tick, action: GameAction) -> ScheduleId {
+        let at = self.clock.get_tick() + delay;
+        self.scheduler.add(at, None, ScheduledTask::Action(action))
+    }
+
+    /// Runs the task every `every` ticks, starting `every` ticks from now.
+    pub fn schedule_repeating(&mut self, every: Tick, task: ScheduledTask) -> ScheduleId {
+        let every = every.max(1);
+        let at = self.clock.get_tick() + every;
+        self.scheduler.add(at, Some(every), task)
+    }
+
+    pub fn cancel_scheduled(&mut self, id: ScheduleId) -> bool {
+        self.scheduler.cancel(id)
+    }
+
+    fn fire_scheduled_events(&mut self) {
+        let now = self.clock.get_tick();
+
+        for event in self.scheduler.take_due(now) {
+            // A task fired earlier in this batch could cancel this one.
+            if !self.scheduler.is_firing(event.id) {
+                continue;
+            }
+
+            match &event.task {
+                ScheduledTask::Action(action) => self.queue_action(action.clone()),
+                ScheduledTask::Timer(timer) => timer(self),
+            }
+
+            self.scheduler.finish_firing(event, now);
+        }
+    }
+
+    /// Events published while dispatching wait for the next call, so subscribers which keep
+    /// reacting to each other cannot stall the tick.
+    fn dispatch_events(&mut self) {
+        let events = self.events.take_pending();
+        let subscribers = self.events.get_subscribers();
+
+        for event in &events {
+            for subscriber in &subscribers {
+                subscriber(self, event);
+            }
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 // ----------------------------------------------------------------------------------------------------
+// Time
+// ----------------------------------------------------------------------------------------------------
+
+pub type Tick = u64;
+
+pub const DEFAULT_PULSES_PER_SECOND: u32 = 10;
+
+pub struct GameClock {
+    pulse: Duration,
+    tick: Tick,
+    next_pulse: Instant,
+}
+
+impl GameClock {
+    pub fn new(pulses_per_second: u32) -> Self {
+        let pulse = Duration::from_secs(1) / pulses_per_second.max(1);
+
+        Self {
+            pulse,
+            tick: 0,
+            next_pulse: Instant::now() + pulse,
+        }
+    }
+
+    pub fn get_tick(&self) -> Tick {
+        self.tick
+    }
+
+    pub fn get_pulse(&self) -> Duration {
+        self.pulse
+    }
+
+    /// How many ticks pass in the seconds.
+    pub fn ticks_in(&self, seconds: u64) -> Tick {
+        (Duration::from_secs(seconds).as_nanos() / self.pulse.as_nanos().max(1)) as Tick
+    }
+
+    /// Sleeps until the next pulse is due. A lagging clock skips the missed pulses instead of
+    /// trying to catch up with a burst of them.
+    pub fn wait_next_pulse(&mut self) {
+        let now = Instant::now();
+
+        if now < self.next_pulse {
+            thread::sleep(self.next_pulse - now);
+            self.next_pulse += self.pulse;
+        } else {
+            self.next_pulse = now + self.pulse;
+        }
+    }
+
+    fn advance(&mut self) {
+        self.tick += 1;
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, From)]
+pub struct ScheduleId(u64);
+
+pub enum ScheduledTask {
+    Action(GameAction),
+    Timer(Rc<dyn Fn(&mut Game)>),
+}
+
+pub struct ScheduledEvent {
+    id: ScheduleId,
+    at: Tick,
+    every: Option<Tick>,
+    task: ScheduledTask,
+}
+
+pub struct Scheduler {
+    next_id: u64,
+    events: Vec<ScheduledEvent>,
+    firing: HashSet<ScheduleId>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            events: Vec::new(),
+            firing: HashSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, at: Tick, every: Option<Tick>, task: ScheduledTask) -> ScheduleId {
+        let id = ScheduleId::from(self.next_id);
+        self.next_id += 1;
+
+        self.events.push(ScheduledEvent {
+            id,
+            at,
+            every,
+            task,
+        });
+
+        id
+    }
+
+    pub fn cancel(&mut self, id: ScheduleId) -> bool {
+        let len_before = self.events.len();
+        self.events.retain(|e| e.id != id);
+
+        self.firing.remove(&id) || self.events.len() != len_before
+    }
+
+    fn take_due(&mut self, now: Tick) -> Vec<ScheduledEvent> {
+        let (mut due, pending) = self
+            .events
+            .drain(..)
+            .partition(|e: &ScheduledEvent| e.at <= now);
+        self.events = pending;
+        due.sort_by_key(|e: &ScheduledEvent| (e.at, e.id.0));
+
+        self.firing = due.iter().map(|e| e.id).collect();
+
+        due
+    }
+
+    fn is_firing(&self, id: ScheduleId) -> bool {
+        self.firing.contains(&id)
+    }
+
+    /// Puts a repeating event back in the queue unless it was cancelled while firing.
+    fn finish_firing(&mut self, mut event: ScheduledEvent, now: Tick) {
+        if !self.firing.remove(&event.id) {
+            return;
+        }
+
+        if let Some(every) = event.every {
+            event.at = now + every;
+            self.events.push(event);
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Events
+// ----------------------------------------------------------------------------------------------------
+
+/// Something which happened in the game. Subsystems like scripting or logging subscribe to the
+/// events instead of being called from the code which makes them happen.
+#[derive(Clone)]
+pub enum GameEvent {
+    PlayerConnected(PlayerId),
+    PlayerDisconnected(PlayerId),
+    CharacterSpawned {
+        who: CharacterRef,
+        room: Rc<Room>,
+    },
+    CharacterMoved {
+        who: CharacterRef,
+        from: Rc<Room>,
+        to: Rc<Room>,
+    },
+    CharacterDied {
+        who: CharacterRef,
+        room: Rc<Room>,
+        killer: Option<CharacterRef>,
+    },
+    SaidInRoom {
+        who: CharacterRef,
+        room: Rc<Room>,
+        text: String,
+    },
+    ItemTaken {
+        who: CharacterRef,
+        room: Rc<Room>,
+        item: ItemId,
+    },
+    /// The game refused what the character tried to do.
+    ActionRejected {
+        who: CharacterRef,
+        reason: String,
+    },
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |char: &CharacterRef| char.borrow().get_name();
+
+        match self {
+            GameEvent::PlayerConnected(id) => write!(f, "{:?} connected", id),
+            GameEvent::PlayerDisconnected(id) => write!(f, "{:?} disconnected", id),
+            GameEvent::CharacterSpawned { who, room } => {
+                write!(f, "{} spawned in room {}", name(who), room.get_id())
+            }
+            GameEvent::CharacterMoved { who, from, to } => write!(
+                f,
+                "{} moved from room {} to room {}",
+                name(who),
+                from.get_id(),
+                to.get_id()
+            ),
+            GameEvent::CharacterDied { who, room, killer } => match killer {
+                Some(killer) => write!(
+                    f,
+                    "{} was killed by {} in room {}",
+                    name(who),
+                    name(killer),
+                    room.get_id()
+                ),
+                None => write!(f, "{} died in room {}", name(who), room.get_id()),
+            },
+            GameEvent::SaidInRoom { who, room, text } => {
+                write!(f, "{} said '{}' in room {}", name(who), text, room.get_id())
+            }
+            GameEvent::ItemTaken { who, room, item } => {
+                write!(
+                    f,
+                    "{} took item {} in room {}",
+                    name(who),
+                    item,
+                    room.get_id()
+                )
+            }
+            GameEvent::ActionRejected { who, reason } => {
+                write!(f, "{} was refused: {}", name(who), reason)
+            }
+        }
+    }
+}
+
+pub type Subscriber = Rc<dyn Fn(&mut Game, &GameEvent)>;
+
+pub struct EventBus {
+    subscribers: Vec<Subscriber>,
+    pending: VecDeque<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// A bus with the subscribers every game needs, like the one which runs the scripts.
+    pub fn with_builtins() -> Self {
+        let mut bus = Self::new();
+        bus.subscribe(Rc::new(script::on_game_event));
+
+        bus
+    }
+
+    pub fn subscribe(&mut self, subscriber: Subscriber) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        self.pending.push_back(event);
+    }
+
+    fn take_pending(&mut self) -> Vec<GameEvent> {
+        self.pending.drain(..).collect()
+    }
+
+    /// The subscribers are shared so that they can run against the game mutably.
+    fn get_subscribers(&self) -> Vec<Subscriber> {
+        self.subscribers.clone()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, From, Into)]
 pub struct PlayerId(u32);
 
 pub struct Player {
     id: PlayerId,
     main_char: Option<CharacterRef>,
+    account: Option<String>,
 }
 
 impl Player {
@@ -140,6 +745,7 @@ impl Player {
         Self {
             id,
             main_char: None,
+            account: None,
         }
     }
 
@@ -158,6 +764,15 @@ impl Player {
     pub fn unset_main_char(&mut self) {
         self.main_char = None;
     }
+
+    /// The account the character in play is saved to.
+    pub fn get_account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    pub fn set_account(&mut self, account: String) {
+        self.account = Some(account);
+    }
 }
 
 impl PartialEq for Player {
@@ -178,9 +793,114 @@ impl fmt::Debug for Player {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Player")
             .field("id", &self.id)
-            .field("main_char", &self.main_char.as_ref().map(|char| char.borrow().get_id()))
+            .field(
+                "main_char",
+                &self.main_char.as_ref().map(|char| char.borrow().get_id()),
+            )
+            .field("account", &self.account)
             .finish()
     }
 }
 
 pub type PlayerRef = Rc<RefCell<Player>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn counting_timer(count: &Rc<Cell<u32>>) -> ScheduledTask {
+        let count = count.clone();
+        ScheduledTask::Timer(Rc::new(move |_: &mut Game| count.set(count.get() + 1)))
+    }
+
+    #[test]
+    fn clock_counts_ticks_by_its_pulse_rate() {
+        assert_eq!(GameClock::new(10).ticks_in(3), 30);
+        assert_eq!(GameClock::new(1).ticks_in(60), 60);
+        assert_eq!(GameClock::new(0).ticks_in(2), 2);
+    }
+
+    #[test]
+    fn one_shot_task_fires_once_when_due() {
+        let mut game = Game::new();
+        let count = Rc::new(Cell::new(0));
+        game.scheduler.add(2, None, counting_timer(&count));
+
+        game.tick();
+        assert_eq!(count.get(), 0);
+        game.tick();
+        assert_eq!(count.get(), 1);
+        game.tick();
+        game.tick();
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn repeating_task_fires_every_period_until_cancelled() {
+        let mut game = Game::new();
+        let count = Rc::new(Cell::new(0));
+        let id = game.schedule_repeating(3, counting_timer(&count));
+
+        for _ in 0..9 {
+            game.tick();
+        }
+        assert_eq!(count.get(), 3);
+
+        assert!(game.cancel_scheduled(id));
+        for _ in 0..9 {
+            game.tick();
+        }
+        assert_eq!(count.get(), 3);
+    }
+
+    #[test]
+    fn task_cancelled_by_another_in_the_same_batch_does_not_fire() {
+        let mut game = Game::new();
+        let count = Rc::new(Cell::new(0));
+        let victim = Rc::new(Cell::new(None));
+
+        game.schedule_repeating(
+            1,
+            ScheduledTask::Timer(Rc::new({
+                let victim = victim.clone();
+                move |game: &mut Game| {
+                    if let Some(id) = victim.take() {
+                        assert!(game.cancel_scheduled(id));
+                    }
+                }
+            })),
+        );
+        victim.set(Some(game.schedule_repeating(1, counting_timer(&count))));
+
+        game.tick();
+        game.tick();
+        assert_eq!(count.get(), 0);
+        assert_eq!(game.scheduler.events.len(), 1);
+    }
+
+    #[test]
+    fn repeating_task_cancelling_itself_is_not_rescheduled() {
+        let mut game = Game::new();
+        let count = Rc::new(Cell::new(0));
+        let own_id = Rc::new(Cell::new(None));
+
+        let id = game.schedule_repeating(
+            1,
+            ScheduledTask::Timer(Rc::new({
+                let count = count.clone();
+                let own_id = own_id.clone();
+                move |game: &mut Game| {
+                    count.set(count.get() + 1);
+                    game.cancel_scheduled(own_id.get().unwrap());
+                }
+            })),
+        );
+        own_id.set(Some(id));
+
+        game.tick();
+        game.tick();
+        assert_eq!(count.get(), 1);
+        assert!(game.scheduler.events.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+use derive_more::{Display, From, Into};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::area::RoomId;
+use crate::script::ScriptDef;
+use crate::socium::{CharacterId, Stat};
+use crate::utils::BuildRef;
+
+#[derive(Debug, Display, PartialEq, Eq, Hash, Clone, Copy, From, Into)]
+pub struct ItemId(u32);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum ItemFlag {
+    /// Other items can be put inside.
+    Container,
+    /// Stays where it is, like a fountain or a statue.
+    NoTake,
+    /// Rots away some time after the death which left it.
+    Corpse,
+}
+
+/// A place on the body where a character wears or holds an item.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum WearSlot {
+    Head,
+    Neck,
+    Body,
+    Arms,
+    Hands,
+    Waist,
+    Legs,
+    Feet,
+    MainHand,
+    OffHand,
+}
+
+impl WearSlot {
+    pub const ALL: [WearSlot; 10] = [
+        WearSlot::Head,
+        WearSlot::Neck,
+        WearSlot::Body,
+        WearSlot::Arms,
+        WearSlot::Hands,
+        WearSlot::Waist,
+        WearSlot::Legs,
+        WearSlot::Feet,
+        WearSlot::MainHand,
+        WearSlot::OffHand,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            WearSlot::Head => "head",
+            WearSlot::Neck => "neck",
+            WearSlot::Body => "body",
+            WearSlot::Arms => "arms",
+            WearSlot::Hands => "hands",
+            WearSlot::Waist => "waist",
+            WearSlot::Legs => "legs",
+            WearSlot::Feet => "feet",
+            WearSlot::MainHand => "main hand",
+            WearSlot::OffHand => "off hand",
+        }
+    }
+
+    /// Whether the slot is a hand, which wields rather than wears.
+    pub fn is_hand(&self) -> bool {
+        matches!(self, WearSlot::MainHand | WearSlot::OffHand)
+    }
+}
+
+/// Where an item is. The room, the character or the container lists the item as well.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ItemLocation {
+    Nowhere,
+    Room(RoomId),
+    Character(CharacterId),
+    Container(ItemId),
+    Equipped(CharacterId, WearSlot),
+}
+
+impl ItemLocation {
+    /// The character who carries or wears the item directly.
+    pub fn get_character(&self) -> Option<CharacterId> {
+        match self {
+            ItemLocation::Character(char_id) | ItemLocation::Equipped(char_id, _) => Some(*char_id),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Item {
+    id: ItemId,
+    keywords: Vec<String>,
+    short_description: String,
+    long_description: String,
+    description: String,
+    weight: u32,
+    flags: Vec<ItemFlag>,
+    slots: Vec<WearSlot>,
+    modifiers: BTreeMap<Stat, i32>,
+    scripts: Vec<ScriptDef>,
+    location: ItemLocation,
+    contents: Vec<ItemId>,
+}
+
+impl Item {
+    pub fn new(id: ItemId, keywords: Vec<String>, short_description: String) -> Self {
+        Self {
+            id,
+            keywords,
+            long_description: format!("{} lies here.", capitalize(&short_description)),
+            short_description,
+            description: String::new(),
+            weight: 0,
+            flags: Vec::new(),
+            slots: Vec::new(),
+            modifiers: BTreeMap::new(),
+            scripts: Vec::new(),
+            location: ItemLocation::Nowhere,
+            contents: Vec::new(),
+        }
+    }
+
+    pub fn get_id(&self) -> ItemId {
+        self.id
+    }
+
+    pub fn get_keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    pub fn has_keyword(&self, word: &str) -> bool {
+        self.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+    }
+
+    /// The name in lists and messages, like `a rusty key`.
+    pub fn get_short_description(&self) -> &str {
+        &self.short_description
+    }
+
+    /// The line shown when the item lies in a room.
+    pub fn get_long_description(&self) -> &str {
+        &self.long_description
+    }
+
+    pub fn set_long_description(&mut self, long_description: String) {
+        self.long_description = long_description;
+    }
+
+    /// The text shown when the item is examined.
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, description: String) {
+        self.description = description;
+    }
+
+    pub fn get_weight(&self) -> u32 {
+        self.weight
+    }
+
+    pub fn set_weight(&mut self, weight: u32) {
+        self.weight = weight;
+    }
+
+    pub fn get_flags(&self) -> &[ItemFlag] {
+        &self.flags
+    }
+
+    pub fn has_flag(&self, flag: ItemFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn set_flag(&mut self, flag: ItemFlag) {
+        if !self.has_flag(flag) {
+            self.flags.push(flag);
+        }
+    }
+
+    pub fn unset_flag(&mut self, flag: ItemFlag) {
+        self.flags.retain(|f| *f != flag);
+    }
+
+    /// The slots the item can be worn on, in the order of preference.
+    pub fn get_slots(&self) -> &[WearSlot] {
+        &self.slots
+    }
+
+    pub fn fits_slot(&self, slot: WearSlot) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    pub fn set_slots(&mut self, slots: Vec<WearSlot>) {
+        self.slots = slots;
+    }
+
+    /// What the item adds to the stats of the character who wears it.
+    pub fn get_modifiers(&self) -> &BTreeMap<Stat, i32> {
+        &self.modifiers
+    }
+
+    pub fn set_modifier(&mut self, stat: Stat, value: i32) {
+        self.modifiers.insert(stat, value);
+    }
+
+    pub fn unset_modifier(&mut self, stat: Stat) {
+        self.modifiers.remove(&stat);
+    }
+
+    pub fn get_scripts(&self) -> &[ScriptDef] {
+        &self.scripts
+    }
+
+    pub fn set_scripts(&mut self, scripts: Vec<ScriptDef>) {
+        self.scripts = scripts;
+    }
+
+    pub fn get_location(&self) -> ItemLocation {
+        self.location
+    }
+
+    /// Only the world moves items, so that the location and the lists stay in agreement.
+    pub(crate) fn set_location(&mut self, location: ItemLocation) {
+        self.location = location;
+    }
+
+    pub fn get_contents(&self) -> &[ItemId] {
+        &self.contents
+    }
+
+    pub(crate) fn add_content(&mut self, item_id: ItemId) {
+        if !self.contents.contains(&item_id) {
+            self.contents.push(item_id);
+        }
+    }
+
+    pub(crate) fn remove_content(&mut self, item_id: ItemId) {
+        self.contents.retain(|id| *id != item_id);
+    }
+}
+
+impl BuildRef for Item {
+    fn build_ref(self) -> ItemRef {
+        Rc::new(RefCell::new(self))
+    }
+}
+
+pub type ItemRef = Rc<RefCell<Item>>;
+
+/// Makes the first letter uppercase, so that `a rusty key` can start a sentence.
+pub fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        None => String::new(),
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+    }
+}
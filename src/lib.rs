@@ -0,0 +1,18 @@
+pub mod account;
+pub mod action;
+pub mod area;
+pub mod combat;
+pub mod command;
+pub mod creation;
+pub mod error;
+pub mod game;
+pub mod item;
+pub mod loader;
+pub mod npc;
+pub mod output;
+pub mod script;
+pub mod server;
+pub mod snapshot;
+pub mod socium;
+pub mod target;
+pub mod utils;
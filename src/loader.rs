@@ -0,0 +1,873 @@
+use crate::area::{
+    Door, DoorState, ExitKey, MoveDirection, Room, RoomId, World, WorldId, WorldRef,
+};
+use crate::error::GameError;
+use crate::game::Game;
+use crate::item::{Item, ItemFlag, ItemId, ItemLocation, WearSlot};
+use crate::npc::{BehaviorDef, NpcTemplate};
+use crate::script::{self, ScriptDef, ScriptOwner};
+use crate::socium::Stat;
+use crate::utils::BuildRef;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// A world as builders describe it in a world file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorldDef {
+    pub id: u32,
+    pub name: String,
+    /// The room where characters appear and come back after death. The first room by default.
+    #[serde(default)]
+    pub spawn: Option<u32>,
+    pub rooms: Vec<RoomDef>,
+    #[serde(default)]
+    pub npc_templates: Vec<NpcTemplate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoomDef {
+    pub id: u32,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub short_description: Option<String>,
+    #[serde(default)]
+    pub zone: Option<String>,
+    #[serde(default)]
+    pub exits: BTreeMap<MoveDirection, u32>,
+    /// Exits used by typing their keyword, like `out` or `climb ladder`.
+    #[serde(default)]
+    pub custom_exits: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub doors: Vec<DoorDef>,
+    /// Items lying on the floor.
+    #[serde(default)]
+    pub items: Vec<ItemDef>,
+    /// Templates of the NPCs spawned in the room when the world is loaded.
+    #[serde(default)]
+    pub npcs: Vec<u32>,
+    #[serde(default)]
+    pub scripts: Vec<ScriptDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemDef {
+    pub id: u32,
+    pub keywords: Vec<String>,
+    pub short_description: String,
+    #[serde(default)]
+    pub long_description: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub weight: u32,
+    #[serde(default)]
+    pub flags: Vec<ItemFlag>,
+    /// Where the item can be worn or wielded, the preferred slot first.
+    #[serde(default)]
+    pub slots: Vec<WearSlot>,
+    /// What the item adds to the stats of the wearer.
+    #[serde(default)]
+    pub modifiers: BTreeMap<Stat, i32>,
+    #[serde(default)]
+    pub scripts: Vec<ScriptDef>,
+    /// Items inside a container.
+    #[serde(default)]
+    pub contents: Vec<ItemDef>,
+}
+
+impl ItemDef {
+    /// Describes an existing item with everything inside it.
+    pub fn from_item(world: &World, item: &Item) -> Self {
+        let contents = item
+            .get_contents()
+            .iter()
+            .filter_map(|id| world.get_item(*id))
+            .map(|content| ItemDef::from_item(world, &content.borrow()))
+            .collect();
+
+        Self {
+            id: item.get_id().into(),
+            keywords: item.get_keywords().to_vec(),
+            short_description: item.get_short_description().to_string(),
+            long_description: Some(item.get_long_description().to_string()),
+            description: item.get_description().to_string(),
+            weight: item.get_weight(),
+            flags: item.get_flags().to_vec(),
+            slots: item.get_slots().to_vec(),
+            modifiers: item.get_modifiers().clone(),
+            scripts: item.get_scripts().to_vec(),
+            contents,
+        }
+    }
+
+    /// Creates the item with everything inside it at the location.
+    pub(crate) fn build(
+        &self,
+        world: &mut World,
+        location: ItemLocation,
+    ) -> Result<ItemId, GameError> {
+        let mut item = Item::new(
+            ItemId::from(self.id),
+            self.keywords.clone(),
+            self.short_description.clone(),
+        );
+        if let Some(long_description) = &self.long_description {
+            item.set_long_description(long_description.clone());
+        }
+        item.set_description(self.description.clone());
+        item.set_weight(self.weight);
+        for flag in &self.flags {
+            item.set_flag(*flag);
+        }
+        item.set_slots(self.slots.clone());
+        for (stat, value) in &self.modifiers {
+            item.set_modifier(*stat, *value);
+        }
+        item.set_scripts(self.scripts.clone());
+
+        let id = world.add_item(item, location)?;
+        for content in &self.contents {
+            content.build(world, ItemLocation::Container(id))?;
+        }
+
+        Ok(id)
+    }
+
+    /// Gives a new id to the item and everything inside it whose id is taken in the world
+    /// already. The others keep theirs, so that a key still opens its door.
+    pub(crate) fn with_free_ids(&self, world: &mut World) -> ItemDef {
+        let mut def = self.clone();
+        if world.get_item(ItemId::from(def.id)).is_some() {
+            def.id = world.allocate_item_id().into();
+        }
+        def.contents = self
+            .contents
+            .iter()
+            .map(|content| content.with_free_ids(world))
+            .collect();
+
+        def
+    }
+
+    /// Calls the function for the item and everything inside it.
+    pub fn visit(&self, f: &mut impl FnMut(&ItemDef)) {
+        f(self);
+        for content in &self.contents {
+            content.visit(f);
+        }
+    }
+}
+
+/// A door on one of the room exits. The way back from the other room gets the same door, so a
+/// two-way passage needs it described only once.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DoorDef {
+    /// A direction like `north` or `n`, or the keyword of a custom exit.
+    pub exit: String,
+    #[serde(default = "DoorDef::default_name")]
+    pub name: String,
+    #[serde(default = "DoorDef::default_state")]
+    pub state: DoorState,
+    #[serde(default)]
+    pub key: Option<u32>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+impl DoorDef {
+    fn default_name() -> String {
+        String::from("door")
+    }
+
+    fn default_state() -> DoorState {
+        DoorState::Closed
+    }
+
+    fn from_door(exit: &ExitKey, door: &Door) -> Self {
+        Self {
+            exit: exit.get_name().to_string(),
+            name: door.get_name().to_string(),
+            state: door.get_state(),
+            key: door.get_key().map(u32::from),
+            hidden: door.is_hidden(),
+            secret: door.is_secret(),
+        }
+    }
+
+    fn build_door(&self) -> Door {
+        let mut door = Door::new(self.name.clone(), self.state);
+        if let Some(key) = self.key {
+            door.set_key(ItemId::from(key));
+        }
+        door.set_hidden(self.hidden);
+        door.set_secret(self.secret);
+
+        door
+    }
+}
+
+impl RoomDef {
+    /// Describes an existing room. Exits to rooms which are gone are left out.
+    pub fn from_room(room: &Room) -> Self {
+        let mut exits = BTreeMap::new();
+        let mut custom_exits = BTreeMap::new();
+        let mut doors = Vec::new();
+
+        let items = {
+            let world = room.get_world().borrow();
+
+            room.get_items()
+                .iter()
+                .filter_map(|id| world.get_item(*id))
+                .map(|item| ItemDef::from_item(&world, &item.borrow()))
+                .collect()
+        };
+
+        for (exit, to) in room.get_pathways() {
+            if let Some(door) = room.get_door(&exit) {
+                doors.push(DoorDef::from_door(&exit, &door.borrow()));
+            }
+
+            match exit {
+                ExitKey::Direction(direction) => exits.insert(direction, to.get_id().into()),
+                ExitKey::Keyword(keyword) => custom_exits.insert(keyword, to.get_id().into()),
+            };
+        }
+
+        Self {
+            id: room.get_id().into(),
+            title: room.get_title().to_string(),
+            description: room.get_description().to_string(),
+            short_description: room.get_short_description().clone(),
+            zone: room.get_zone().clone(),
+            exits,
+            custom_exits,
+            doors,
+            items,
+            npcs: Vec::new(),
+            scripts: room.get_scripts().to_vec(),
+        }
+    }
+
+    /// Lists both the direction and the keyword exits.
+    pub fn get_exit_defs(&self) -> Vec<(ExitKey, u32)> {
+        let directions = self.exits.iter().map(|(d, to)| (ExitKey::from(*d), *to));
+        let keywords = self
+            .custom_exits
+            .iter()
+            .map(|(k, to)| (ExitKey::keyword(k), *to));
+
+        directions.chain(keywords).collect()
+    }
+}
+
+impl WorldDef {
+    /// Finds every problem which prevents building the world.
+    pub fn validate(&self) -> Vec<WorldDefError> {
+        let mut problems = Vec::new();
+        let mut room_ids = HashSet::new();
+
+        if self.rooms.is_empty() {
+            problems.push(WorldDefError::NoRooms);
+        }
+
+        for room in &self.rooms {
+            if !room_ids.insert(room.id) {
+                problems.push(WorldDefError::DuplicateRoom(RoomId::from(room.id)));
+            }
+        }
+
+        if let Some(spawn) = self.spawn
+            && !room_ids.contains(&spawn)
+        {
+            problems.push(WorldDefError::UnknownSpawnRoom(RoomId::from(spawn)));
+        }
+
+        let mut template_ids = HashSet::new();
+        for template in &self.npc_templates {
+            if !template_ids.insert(template.id) {
+                problems.push(WorldDefError::DuplicateNpcTemplate(template.id));
+            }
+        }
+        for room in &self.rooms {
+            for template in &room.npcs {
+                if !template_ids.contains(template) {
+                    problems.push(WorldDefError::UnknownNpcTemplate {
+                        room: RoomId::from(room.id),
+                        template: *template,
+                    });
+                }
+            }
+        }
+
+        self.check_patrol_routes(&mut problems);
+
+        let mut item_ids = HashSet::new();
+        for item in self.rooms.iter().flat_map(|room| room.items.iter()) {
+            item.visit(&mut |item| {
+                if !item_ids.insert(item.id) {
+                    problems.push(WorldDefError::DuplicateItem(ItemId::from(item.id)));
+                }
+                if !item.contents.is_empty() && !item.flags.contains(&ItemFlag::Container) {
+                    problems.push(WorldDefError::NotAContainer(ItemId::from(item.id)));
+                }
+                check_scripts(
+                    ScriptOwner::Item(ItemId::from(item.id)),
+                    &item.scripts,
+                    &mut problems,
+                );
+            });
+        }
+
+        for room in &self.rooms {
+            check_scripts(
+                ScriptOwner::Room(RoomId::from(room.id)),
+                &room.scripts,
+                &mut problems,
+            );
+        }
+        for template in &self.npc_templates {
+            check_scripts(
+                ScriptOwner::NpcTemplate(template.id),
+                &template.scripts,
+                &mut problems,
+            );
+        }
+
+        for room in &self.rooms {
+            for keyword in room.custom_exits.keys() {
+                let key = ExitKey::keyword(keyword);
+                if key.get_name().is_empty() || MoveDirection::from_name(key.get_name()).is_some() {
+                    problems.push(WorldDefError::InvalidExitKeyword {
+                        room: RoomId::from(room.id),
+                        keyword: keyword.clone(),
+                    });
+                }
+            }
+
+            let exit_keys: Vec<ExitKey> =
+                room.get_exit_defs().into_iter().map(|(e, _)| e).collect();
+            for door in &room.doors {
+                if !exit_keys.contains(&ExitKey::from_name(&door.exit)) {
+                    problems.push(WorldDefError::DoorWithoutExit {
+                        room: RoomId::from(room.id),
+                        exit: door.exit.clone(),
+                    });
+                }
+                if let Some(key) = door.key
+                    && !item_ids.contains(&key)
+                {
+                    problems.push(WorldDefError::UnknownKey {
+                        room: RoomId::from(room.id),
+                        exit: door.exit.clone(),
+                        key: ItemId::from(key),
+                    });
+                }
+            }
+
+            for (exit, to) in room.get_exit_defs() {
+                if !room_ids.contains(&to) {
+                    problems.push(WorldDefError::DanglingExit {
+                        room: RoomId::from(room.id),
+                        exit,
+                        to: RoomId::from(to),
+                    });
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Follows the route of every patrol from each room spawning it, so that a misspelled exit
+    /// is found now rather than leaving the NPC standing still.
+    fn check_patrol_routes(&self, problems: &mut Vec<WorldDefError>) {
+        let rooms: HashMap<u32, &RoomDef> = self.rooms.iter().map(|r| (r.id, r)).collect();
+
+        for room in &self.rooms {
+            for template in self
+                .npc_templates
+                .iter()
+                .filter(|t| room.npcs.contains(&t.id))
+            {
+                for behavior in &template.behaviors {
+                    let BehaviorDef::Patrol { route } = behavior else {
+                        continue;
+                    };
+
+                    let mut at = room;
+                    for name in route {
+                        let exit = ExitKey::from_name(name);
+                        let next = at
+                            .get_exit_defs()
+                            .into_iter()
+                            .find(|(key, _)| *key == exit)
+                            .and_then(|(_, to)| rooms.get(&to));
+                        let Some(next) = next else {
+                            problems.push(WorldDefError::BrokenPatrolRoute {
+                                template: template.id,
+                                room: RoomId::from(at.id),
+                                exit: name.clone(),
+                            });
+                            break;
+                        };
+                        at = next;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn check_scripts(owner: ScriptOwner, scripts: &[ScriptDef], problems: &mut Vec<WorldDefError>) {
+    for def in scripts {
+        if let Err(error) = script::check_syntax(&def.code) {
+            problems.push(WorldDefError::InvalidScript { owner, error });
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldDefError {
+    NoRooms,
+    DuplicateRoom(RoomId),
+    InvalidExitKeyword {
+        room: RoomId,
+        keyword: String,
+    },
+    DanglingExit {
+        room: RoomId,
+        exit: ExitKey,
+        to: RoomId,
+    },
+    DoorWithoutExit {
+        room: RoomId,
+        exit: String,
+    },
+    UnknownKey {
+        room: RoomId,
+        exit: String,
+        key: ItemId,
+    },
+    DuplicateItem(ItemId),
+    NotAContainer(ItemId),
+    UnknownSpawnRoom(RoomId),
+    DuplicateNpcTemplate(u32),
+    UnknownNpcTemplate {
+        room: RoomId,
+        template: u32,
+    },
+    InvalidScript {
+        owner: ScriptOwner,
+        error: String,
+    },
+    BrokenPatrolRoute {
+        template: u32,
+        room: RoomId,
+        exit: String,
+    },
+}
+
+impl fmt::Display for WorldDefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldDefError::NoRooms => write!(f, "the world has no rooms"),
+            WorldDefError::DuplicateRoom(id) => write!(f, "room {} is defined more than once", id),
+            WorldDefError::InvalidExitKeyword { room, keyword } => write!(
+                f,
+                "exit keyword '{}' of room {} is empty or a direction",
+                keyword, room
+            ),
+            WorldDefError::DanglingExit { room, exit, to } => write!(
+                f,
+                "exit {} of room {} leads to unknown room {}",
+                exit, room, to
+            ),
+            WorldDefError::DuplicateItem(id) => write!(f, "item {} is defined more than once", id),
+            WorldDefError::NotAContainer(id) => {
+                write!(f, "item {} has contents but is not a container", id)
+            }
+            WorldDefError::UnknownSpawnRoom(id) => write!(f, "spawn room {} does not exist", id),
+            WorldDefError::DuplicateNpcTemplate(id) => {
+                write!(f, "NPC template {} is defined more than once", id)
+            }
+            WorldDefError::UnknownNpcTemplate { room, template } => {
+                write!(f, "room {} spawns unknown NPC template {}", room, template)
+            }
+            WorldDefError::InvalidScript { owner, error } => {
+                write!(f, "a script of {} does not compile: {}", owner, error)
+            }
+            WorldDefError::BrokenPatrolRoute {
+                template,
+                room,
+                exit,
+            } => write!(
+                f,
+                "patrol route of NPC template {} takes exit {} which room {} does not have",
+                template, exit, room
+            ),
+            WorldDefError::DoorWithoutExit { room, exit } => {
+                write!(
+                    f,
+                    "door on exit {} of room {} which has no such exit",
+                    exit, room
+                )
+            }
+            WorldDefError::UnknownKey { room, exit, key } => write!(
+                f,
+                "door on exit {} of room {} takes unknown key item {}",
+                exit, room, key
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: Box<ron::error::SpannedError>,
+    },
+    Invalid {
+        path: PathBuf,
+        problems: Vec<WorldDefError>,
+    },
+    Game(GameError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, error } => {
+                write!(f, "cannot read {}: {}", path.display(), error)
+            }
+            LoadError::Parse { path, error } => {
+                write!(f, "cannot parse {}: {}", path.display(), error)
+            }
+            LoadError::Invalid { path, problems } => {
+                write!(f, "invalid world in {}:", path.display())?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            LoadError::Game(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<GameError> for LoadError {
+    fn from(error: GameError) -> Self {
+        LoadError::Game(error)
+    }
+}
+
+pub fn read_world_file(path: &Path) -> Result<WorldDef, LoadError> {
+    let text = fs::read_to_string(path).map_err(|error| LoadError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    ron::from_str(&text).map_err(|error| LoadError::Parse {
+        path: path.to_path_buf(),
+        error: Box::new(error),
+    })
+}
+
+/// Reads, validates and builds the world described in the file.
+pub fn load_world_file(game: &mut Game, path: &Path) -> Result<WorldId, LoadError> {
+    let def = read_world_file(path)?;
+
+    let problems = def.validate();
+    if !problems.is_empty() {
+        return Err(LoadError::Invalid {
+            path: path.to_path_buf(),
+            problems,
+        });
+    }
+
+    let world_id = game.add_world(World::new(WorldId::from(def.id), def.name.clone()))?;
+    let world = game.require_world(world_id)?;
+    build_rooms(world, &def.rooms)?;
+    if let Some(spawn) = def.spawn {
+        world.borrow_mut().set_spawn_room(RoomId::from(spawn));
+    }
+
+    let mut world = world.borrow_mut();
+    for template in &def.npc_templates {
+        world.add_npc_template(template.clone())?;
+    }
+    for room_def in &def.rooms {
+        let room = world
+            .get_room(RoomId::from(room_def.id))
+            .ok_or(GameError::UnknownRoom(RoomId::from(room_def.id)))?
+            .clone();
+        for template in &room_def.npcs {
+            world.spawn_npc(*template, &room)?;
+        }
+    }
+
+    Ok(world_id)
+}
+
+/// Creates the rooms in the world, links their exits and puts their items. The definitions must
+/// be validated.
+pub(crate) fn build_rooms(world: &WorldRef, defs: &[RoomDef]) -> Result<(), GameError> {
+    let rooms: Vec<Rc<Room>> = defs
+        .iter()
+        .map(|def| {
+            let mut room = Room::new_in_void(RoomId::from(def.id), world.clone());
+            room.set_title(def.title.clone());
+            room.set_description(def.description.clone());
+            if let Some(short) = &def.short_description {
+                room.set_short_description(short.clone());
+            }
+            if let Some(zone) = &def.zone {
+                room.set_zone(zone.clone());
+            }
+            room.set_scripts(def.scripts.clone());
+
+            Rc::new(room)
+        })
+        .collect();
+
+    let by_id: HashMap<u32, Rc<Room>> = defs
+        .iter()
+        .zip(rooms.iter())
+        .map(|(def, room)| (def.id, room.clone()))
+        .collect();
+
+    for (def, room) in defs.iter().zip(rooms.iter()) {
+        for (exit, to) in def.get_exit_defs() {
+            room.set_exit(exit, by_id[&to].clone());
+        }
+    }
+
+    // A door described on either side is put on both, whichever side comes first wins.
+    for (def, room) in defs.iter().zip(rooms.iter()) {
+        for door_def in &def.doors {
+            let exit = ExitKey::from_name(&door_def.exit);
+            if room.get_door(&exit).is_some() {
+                continue;
+            }
+
+            let door = door_def.build_door().build_ref();
+            room.set_door(&exit, door.clone());
+
+            if let Ok(Some(to)) = room.get_exit_target(&exit) {
+                let opposite = match &exit {
+                    ExitKey::Direction(direction) => Some(ExitKey::from(direction.opposite())),
+                    ExitKey::Keyword(_) => None,
+                };
+
+                if let Some(back) = to.find_exit_to(room, opposite.as_ref())
+                    && to.get_door(&back).is_none()
+                {
+                    to.set_door(&back, door);
+                }
+            }
+        }
+    }
+
+    let mut world_mut = world.borrow_mut();
+    for room in rooms {
+        world_mut.add_room(room);
+    }
+
+    for def in defs {
+        for item in &def.items {
+            item.build(&mut world_mut, ItemLocation::Room(RoomId::from(def.id)))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> WorldDef {
+        ron::from_str(text).expect("the test world should parse")
+    }
+
+    /// Two rooms joined north to south, with a door on the north side of the first one.
+    const TWO_ROOMS: &str = r#"(
+        id: 1,
+        name: "Test",
+        rooms: [
+            (
+                id: 1,
+                title: "Hall",
+                exits: {North: 2},
+                doors: [(exit: "north", name: "gate", state: Closed)],
+            ),
+            (id: 2, title: "Yard", exits: {South: 1}),
+        ],
+    )"#;
+
+    #[test]
+    fn valid_world_has_no_problems() {
+        assert!(parse(TWO_ROOMS).validate().is_empty());
+    }
+
+    #[test]
+    fn dangling_exit_is_reported() {
+        let def =
+            parse(r#"(id: 1, name: "Test", rooms: [(id: 1, title: "Hall", exits: {East: 9})])"#);
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::DanglingExit { room, exit: ExitKey::Direction(MoveDirection::East), to }]
+                if *room == RoomId::from(1) && *to == RoomId::from(9)
+        ));
+    }
+
+    #[test]
+    fn duplicate_room_is_reported() {
+        let def = parse(
+            r#"(id: 1, name: "Test", rooms: [(id: 1, title: "Hall"), (id: 1, title: "Yard")])"#,
+        );
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::DuplicateRoom(id)] if *id == RoomId::from(1)
+        ));
+    }
+
+    #[test]
+    fn duplicate_item_is_reported_inside_containers() {
+        let def = parse(
+            r#"(
+                id: 1,
+                name: "Test",
+                rooms: [
+                    (
+                        id: 1,
+                        title: "Hall",
+                        items: [
+                            (id: 5, keywords: ["key"], short_description: "a key"),
+                            (
+                                id: 6,
+                                keywords: ["bag"],
+                                short_description: "a bag",
+                                flags: [Container],
+                                contents: [(id: 5, keywords: ["coin"], short_description: "a coin")],
+                            ),
+                        ],
+                    ),
+                ],
+            )"#,
+        );
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::DuplicateItem(id)] if *id == ItemId::from(5)
+        ));
+    }
+
+    #[test]
+    fn door_without_exit_is_reported() {
+        let def = parse(
+            r#"(
+                id: 1,
+                name: "Test",
+                rooms: [(id: 1, title: "Hall", doors: [(exit: "west")])],
+            )"#,
+        );
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::DoorWithoutExit { room, exit }]
+                if *room == RoomId::from(1) && exit == "west"
+        ));
+    }
+
+    #[test]
+    fn door_with_unknown_key_is_reported() {
+        let def = parse(
+            r#"(
+                id: 1,
+                name: "Test",
+                rooms: [
+                    (
+                        id: 1,
+                        title: "Hall",
+                        exits: {North: 2},
+                        doors: [(exit: "north", key: Some(4))],
+                        items: [(id: 3, keywords: ["key"], short_description: "a key")],
+                    ),
+                    (id: 2, title: "Yard", exits: {South: 1}, doors: [(exit: "south", key: Some(3))]),
+                ],
+            )"#,
+        );
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::UnknownKey { room, exit, key }]
+                if *room == RoomId::from(1) && exit == "north" && *key == ItemId::from(4)
+        ));
+    }
+
+    #[test]
+    fn broken_patrol_route_is_reported() {
+        let def = parse(
+            r#"(
+                id: 1,
+                name: "Test",
+                rooms: [
+                    (id: 1, title: "Hall", exits: {North: 2}, npcs: [7]),
+                    (id: 2, title: "Yard", exits: {South: 1}),
+                ],
+                npc_templates: [
+                    (id: 7, name: "guard", behaviors: [Patrol(route: ["north", "nroth"])]),
+                ],
+            )"#,
+        );
+
+        let problems = def.validate();
+        assert!(matches!(
+            problems.as_slice(),
+            [WorldDefError::BrokenPatrolRoute { template: 7, room, exit }]
+                if *room == RoomId::from(2) && exit == "nroth"
+        ));
+    }
+
+    #[test]
+    fn door_is_shared_by_both_sides() {
+        let def = parse(TWO_ROOMS);
+        let world = World::new(WorldId::from(1), def.name.clone()).build_ref();
+        build_rooms(&world, &def.rooms).unwrap();
+
+        let world = world.borrow();
+        let hall = world.get_room(RoomId::from(1)).unwrap();
+        let yard = world.get_room(RoomId::from(2)).unwrap();
+        let front = hall.get_door(&ExitKey::from(MoveDirection::North)).unwrap();
+        let back = yard.get_door(&ExitKey::from(MoveDirection::South)).unwrap();
+        assert!(Rc::ptr_eq(&front, &back));
+
+        front.borrow_mut().set_state(DoorState::Open);
+        assert!(back.borrow().is_open());
+    }
+}
@@ -1,38 +1,201 @@
-mod action;
-mod area;
-mod game;
-mod socium;
-mod utils;
+use mud_jam_solo::account::{self, AccountStore, AccountStoreRef};
+use mud_jam_solo::area::WorldId;
+use mud_jam_solo::combat;
+use mud_jam_solo::creation::{self, CreationRules};
+use mud_jam_solo::game::{DEFAULT_PULSES_PER_SECOND, Game, GameClock, GameEvent, ScheduledTask};
+use mud_jam_solo::loader;
+use mud_jam_solo::server::Server;
+use mud_jam_solo::snapshot;
+use mud_jam_solo::utils::BuildRef;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::area::WorldId;
-use crate::game::{Game, Player, PlayerId};
-use crate::socium::{Character, CharacterId};
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4000";
+const DEFAULT_WORLD_FILE: &str = "data/worlds/first.ron";
+const DEFAULT_SNAPSHOT_FILE: &str = "data/snapshot.ron";
+const DEFAULT_ACCOUNTS_FILE: &str = "data/accounts.ron";
+const DEFAULT_CREATION_FILE: &str = "data/creation.ron";
+const DEFAULT_AUTOSAVE_PERIOD_SECONDS: u64 = 60;
+const REGENERATION_PERIOD_SECONDS: u64 = 5;
+const COMBAT_ROUND_SECONDS: u64 = 2;
+const NPC_ACTION_SECONDS: u64 = 3;
+const SCRIPT_TICK_SECONDS: u64 = 1;
 
 fn main() {
     let mut game = Game::new();
 
-    let world_a = area::World::new(WorldId::from(1), String::from("First world"));
+    let pulses_per_second = std::env::var("MUD_PULSES_PER_SECOND")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&pulses: &u32| pulses > 0)
+        .unwrap_or(DEFAULT_PULSES_PER_SECOND);
+    *game.get_clock_mut() = GameClock::new(pulses_per_second);
+    game.get_output_mut()
+        .set_logging(std::env::var("MUD_LOG_OUTPUT").is_ok_and(|v| v == "1"));
 
-    let world_a_id = game.add_world(world_a);
-    game.fill_world(world_a_id);
+    let snapshot_file = PathBuf::from(
+        std::env::var("MUD_SNAPSHOT_FILE").unwrap_or_else(|_| String::from(DEFAULT_SNAPSHOT_FILE)),
+    );
+    let world_a_id = if snapshot_file.exists() {
+        restore_game(&mut game, &snapshot_file)
+    } else {
+        load_world(&mut game)
+    };
     println!(">>> world = {:#?}", game.get_world(world_a_id).unwrap());
+    combat::schedule_corpse_decays(&mut game);
 
-    let char_a = Character::new(CharacterId::from(11));
-    let player_a = Player::new(PlayerId::from(21));
-    println!(">>> player_a in VOID = {:#?}", player_a);
+    for world in game.get_worlds() {
+        let world = world.borrow();
+        for issue in world.validate_geometry() {
+            eprintln!("Warning: world {:?}: {}", world.get_id(), issue);
+        }
+    }
+
+    let accounts = load_accounts().build_ref();
+
+    let autosave_period_seconds = std::env::var("MUD_AUTOSAVE_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&seconds: &u64| seconds > 0)
+        .unwrap_or(DEFAULT_AUTOSAVE_PERIOD_SECONDS);
+    game.schedule_repeating(
+        game.get_clock().ticks_in(autosave_period_seconds),
+        ScheduledTask::Timer(Rc::new({
+            let snapshot_file = snapshot_file.clone();
+            let accounts = accounts.clone();
+            move |game: &mut Game| save_game(game, &snapshot_file, &accounts)
+        })),
+    );
+
+    game.schedule_repeating(
+        game.get_clock().ticks_in(REGENERATION_PERIOD_SECONDS),
+        ScheduledTask::Timer(Rc::new(|game: &mut Game| game.regenerate_characters())),
+    );
+    game.schedule_repeating(
+        game.get_clock().ticks_in(COMBAT_ROUND_SECONDS),
+        ScheduledTask::Timer(Rc::new(|game: &mut Game| game.queue_combat_rounds())),
+    );
+    game.schedule_repeating(
+        game.get_clock().ticks_in(NPC_ACTION_SECONDS),
+        ScheduledTask::Timer(Rc::new(|game: &mut Game| game.run_behaviors())),
+    );
+    game.schedule_repeating(
+        game.get_clock().ticks_in(SCRIPT_TICK_SECONDS),
+        ScheduledTask::Timer(Rc::new(|game: &mut Game| game.run_tick_scripts())),
+    );
+
+    game.subscribe(Rc::new(log_event));
+
+    let listen_addr =
+        std::env::var("MUD_LISTEN_ADDR").unwrap_or_else(|_| String::from(DEFAULT_LISTEN_ADDR));
+    let creation = Rc::new(load_creation_rules());
+    let mut server = match Server::bind(&listen_addr, world_a_id, accounts.clone(), creation) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Cannot listen on {listen_addr}: {error}");
+            std::process::exit(1);
+        }
+    };
+    println!(">>> Listening on {}", listen_addr);
+
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    if let Err(error) = ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst)) {
+        eprintln!("Cannot handle the shutdown signals: {error}");
+    }
 
-    let player_a_id = game.add_player(player_a);
-    game.spawn_player_character(world_a_id, player_a_id, char_a);
+    while running.load(Ordering::SeqCst) {
+        game.get_clock_mut().wait_next_pulse();
 
-    let player_a = game.get_player(player_a_id).unwrap();
-    println!(">>> player_a after SPAWN = {:#?}", player_a);
-    let player_a = player_a.borrow().get_id();
+        // Execute user actions
+        server.accept_connections(&mut game);
+        server.process_input(&mut game);
 
-    loop {
         // Process events
+        game.tick();
+
+        server.flush_output(&mut game);
+    }
+
+    println!(">>> Shutting down");
+    save_game(&game, &snapshot_file, &accounts);
+}
+
+/// Writes the worlds into the snapshot and the characters in play into their accounts together,
+/// so that an item is never both in a room of the snapshot and in a saved inventory.
+fn save_game(game: &Game, snapshot_file: &Path, accounts: &AccountStoreRef) {
+    if let Err(error) = snapshot::save_to_file(game, snapshot_file) {
+        eprintln!("Cannot save the game: {error}");
+    }
+    account::save_online_characters(game, &mut accounts.borrow_mut());
+}
+
+/// Logs what matters to the operator. Walking and talking happen too often to be worth it, and
+/// the server logs the connections already.
+fn log_event(_game: &mut Game, event: &GameEvent) {
+    match event {
+        GameEvent::CharacterMoved { .. }
+        | GameEvent::SaidInRoom { .. }
+        | GameEvent::PlayerConnected(_)
+        | GameEvent::PlayerDisconnected(_) => {}
+        _ => println!(">>> {event}"),
+    }
+}
+
+fn load_accounts() -> AccountStore {
+    let accounts_file =
+        std::env::var("MUD_ACCOUNTS_FILE").unwrap_or_else(|_| String::from(DEFAULT_ACCOUNTS_FILE));
+
+    match AccountStore::load(Path::new(&accounts_file)) {
+        Ok(accounts) => accounts,
+        Err(error) => {
+            eprintln!("Cannot load the accounts: {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_creation_rules() -> CreationRules {
+    let creation_file =
+        std::env::var("MUD_CREATION_FILE").unwrap_or_else(|_| String::from(DEFAULT_CREATION_FILE));
+
+    match creation::load_rules_file(Path::new(&creation_file)) {
+        Ok(rules) => rules,
+        Err(error) => {
+            eprintln!("Cannot load the creation rules: {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn load_world(game: &mut Game) -> WorldId {
+    let world_file =
+        std::env::var("MUD_WORLD_FILE").unwrap_or_else(|_| String::from(DEFAULT_WORLD_FILE));
+
+    match loader::load_world_file(game, Path::new(&world_file)) {
+        Ok(id) => id,
+        Err(error) => {
+            eprintln!("Cannot load the world: {error}");
+            std::process::exit(1);
+        }
+    }
+}
 
-        // Execute a user action
-        let command = action::ask_command_as_action();
-        command.execute(&mut game, player_a);
+fn restore_game(game: &mut Game, snapshot_file: &Path) -> WorldId {
+    match snapshot::restore_from_file(game, snapshot_file) {
+        Ok(world_ids) if !world_ids.is_empty() => {
+            println!(">>> Restored the game from {}", snapshot_file.display());
+            world_ids[0]
+        }
+        Ok(_) => {
+            eprintln!("No worlds in {}", snapshot_file.display());
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Cannot restore the game: {error}");
+            std::process::exit(1);
+        }
     }
 }
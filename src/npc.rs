@@ -0,0 +1,309 @@
+use crate::action::GameAction;
+use crate::area::{ExitKey, Room, RoomId, World};
+use crate::script::ScriptDef;
+use crate::socium::{CharacterId, CharacterRef, Stat, Vital};
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::rc::{Rc, Weak};
+
+/// The chance, in percent, that a wanderer takes a step when it gets to act.
+const WANDER_CHANCE: u32 = 25;
+
+/// Describes a kind of character the world spawns without a player, like a guard or a rat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpcTemplate {
+    pub id: u32,
+    pub name: String,
+    /// Shown next to the name, like `a mangy mutt`.
+    #[serde(default)]
+    pub short_description: String,
+    /// The words players can target the NPC by, besides the ones of the name.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Stats which differ from the ones of a new character.
+    #[serde(default)]
+    pub stats: BTreeMap<Stat, i32>,
+    /// Vital maximums which differ from the ones of a new character.
+    #[serde(default)]
+    pub vitals: BTreeMap<Vital, i32>,
+    /// The first behavior which wants to do something acts, the rest wait for the next time.
+    #[serde(default)]
+    pub behaviors: Vec<BehaviorDef>,
+    #[serde(default)]
+    pub scripts: Vec<ScriptDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BehaviorDef {
+    /// Walks around now and then without leaving the zone of its room.
+    Wander,
+    /// Stays in the room it was first seen in and returns there when it can.
+    Sentinel,
+    /// Attacks any player character it sees.
+    Aggressive,
+    /// Greets the players who come in. There is no trade yet, so the shopkeeper has no stock and
+    /// selling is left to the scripts of the template.
+    Shopkeeper {
+        #[serde(default)]
+        greeting: Option<String>,
+    },
+    /// Walks the route of exits over and over.
+    Patrol { route: Vec<String> },
+}
+
+impl BehaviorDef {
+    pub fn build(&self) -> Box<dyn Behavior> {
+        match self {
+            BehaviorDef::Wander => Box::new(Wander {}),
+            BehaviorDef::Sentinel => Box::new(Sentinel { post: None }),
+            BehaviorDef::Aggressive => Box::new(Aggressive {}),
+            BehaviorDef::Shopkeeper { greeting } => Box::new(Shopkeeper {
+                greeting: greeting.clone(),
+                greeted: HashSet::new(),
+            }),
+            BehaviorDef::Patrol { route } => Box::new(Patrol {
+                route: route.iter().map(|name| ExitKey::from_name(name)).collect(),
+                next: 0,
+                heading: None,
+            }),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Behaviors
+// ----------------------------------------------------------------------------------------------------
+
+/// Drives a character without a player. It decides the same game actions the commands of a player
+/// queue, so everything an NPC does goes through the same checks.
+pub trait Behavior {
+    /// Picks what the NPC does now, if anything.
+    fn act(&mut self, world: &World, npc: &CharacterRef) -> Option<GameAction>;
+}
+
+pub struct Wander {}
+
+pub struct Sentinel {
+    post: Option<Weak<Room>>,
+}
+
+pub struct Aggressive {}
+
+pub struct Shopkeeper {
+    greeting: Option<String>,
+    greeted: HashSet<CharacterId>,
+}
+
+pub struct Patrol {
+    route: Vec<ExitKey>,
+    next: usize,
+    /// Where the last step was going. The route goes on only once the NPC got there.
+    heading: Option<RoomId>,
+}
+
+impl Behavior for Wander {
+    fn act(&mut self, _world: &World, npc: &CharacterRef) -> Option<GameAction> {
+        let room = idle_room(npc)?;
+        if rand::random_range(0..100) >= WANDER_CHANCE {
+            return None;
+        }
+
+        let zone = room.get_zone().clone();
+        let ways: Vec<_> = room
+            .get_pathways()
+            .into_iter()
+            .filter(|(exit, to)| is_passable(&room, exit) && *to.get_zone() == zone)
+            .collect();
+        let (exit, to) = ways.choose(&mut rand::rng())?.clone();
+
+        Some(GameAction::WalkFromTo {
+            who: npc.clone(),
+            from: room,
+            to,
+            exit,
+        })
+    }
+}
+
+impl Behavior for Sentinel {
+    fn act(&mut self, _world: &World, npc: &CharacterRef) -> Option<GameAction> {
+        let room = idle_room(npc)?;
+
+        let Some(post) = self.post.as_ref().and_then(Weak::upgrade) else {
+            self.post = Some(Rc::downgrade(&room));
+            return None;
+        };
+        if Rc::ptr_eq(&post, &room) {
+            return None;
+        }
+
+        // Only a post next door is found, a sentinel chased further stays where it is.
+        let exit = room.find_exit_to(&post, None)?;
+        if !is_passable(&room, &exit) {
+            return None;
+        }
+
+        Some(GameAction::WalkFromTo {
+            who: npc.clone(),
+            from: room,
+            to: post,
+            exit,
+        })
+    }
+}
+
+impl Behavior for Aggressive {
+    fn act(&mut self, world: &World, npc: &CharacterRef) -> Option<GameAction> {
+        let room = idle_room(npc)?;
+
+        let victim = world
+            .get_characters_in_room(&room)
+            .into_iter()
+            .find(|char| {
+                let char = char.borrow();
+                char.get_owner().is_some() && char.get_vital(Vital::HitPoints).get_current() > 0
+            })?;
+
+        Some(GameAction::Engage {
+            attacker: npc.clone(),
+            victim,
+        })
+    }
+}
+
+impl Behavior for Shopkeeper {
+    fn act(&mut self, world: &World, npc: &CharacterRef) -> Option<GameAction> {
+        let room = npc.borrow().get_current_room().clone()?;
+
+        let customers: Vec<CharacterRef> = world
+            .get_characters_in_room(&room)
+            .into_iter()
+            .filter(|char| char.borrow().get_owner().is_some())
+            .collect();
+        let present: HashSet<CharacterId> = customers.iter().map(|c| c.borrow().get_id()).collect();
+        self.greeted.retain(|id| present.contains(id));
+
+        let newcomer = customers
+            .iter()
+            .find(|char| !self.greeted.contains(&char.borrow().get_id()))?;
+        self.greeted.insert(newcomer.borrow().get_id());
+
+        let text = match &self.greeting {
+            Some(greeting) => greeting.clone(),
+            None => format!(
+                "Welcome, {}! Have a look around.",
+                newcomer.borrow().get_name()
+            ),
+        };
+
+        Some(GameAction::Say {
+            who: npc.clone(),
+            text,
+        })
+    }
+}
+
+impl Behavior for Patrol {
+    fn act(&mut self, _world: &World, npc: &CharacterRef) -> Option<GameAction> {
+        let room = idle_room(npc)?;
+
+        if self.heading.take() == Some(room.get_id()) {
+            self.next = (self.next + 1) % self.route.len();
+        }
+
+        // A closed door or a missing exit holds the patrol until the way is clear.
+        let exit = self.route.get(self.next)?.clone();
+        if !is_passable(&room, &exit) {
+            return None;
+        }
+        let to = match room.get_exit_target(&exit) {
+            Ok(Some(to)) => to,
+            Ok(None) => return None,
+            Err(error) => {
+                eprintln!(
+                    ">>> Patrol of {} is stuck: {error}",
+                    npc.borrow().get_name()
+                );
+                return None;
+            }
+        };
+        self.heading = Some(to.get_id());
+
+        Some(GameAction::WalkFromTo {
+            who: npc.clone(),
+            from: room,
+            to,
+            exit,
+        })
+    }
+}
+
+/// The room of the NPC when it is free to do as it likes, that is not fighting.
+fn idle_room(npc: &CharacterRef) -> Option<Rc<Room>> {
+    let npc = npc.borrow();
+    if npc.is_fighting() {
+        return None;
+    }
+
+    npc.get_current_room().clone()
+}
+
+fn is_passable(room: &Room, exit: &ExitKey) -> bool {
+    room.get_door(exit)
+        .is_none_or(|door| door.borrow().is_open())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{Door, DoorState, MoveDirection, WorldId};
+    use crate::socium::Character;
+    use crate::utils::BuildRef;
+
+    /// Where the patrol wants to walk next, if anywhere.
+    fn step(patrol: &mut Patrol, world: &World, npc: &CharacterRef) -> Option<RoomId> {
+        match patrol.act(world, npc)? {
+            GameAction::WalkFromTo { to, .. } => Some(to.get_id()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn patrol_goes_on_only_after_getting_there() {
+        let world = World::new(WorldId::from(1), String::from("Test")).build_ref();
+        let hall = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
+        let yard = Rc::new(Room::new_in_void(RoomId::from(2), world.clone()));
+        let npc_id = CharacterId::from(1);
+        {
+            let mut world = world.borrow_mut();
+            world.add_room(hall.clone());
+            world.add_room(yard.clone());
+            world.link(&hall, MoveDirection::North, &yard).unwrap();
+            world.add_character(Character::new(npc_id)).unwrap();
+            world.teleport_character(npc_id, &hall);
+        }
+        let gate = Door::new(String::from("gate"), DoorState::Closed).build_ref();
+        hall.set_door(&MoveDirection::North.into(), gate.clone());
+
+        let world = world.borrow();
+        let npc = world.get_character(npc_id).unwrap().clone();
+        let mut patrol = Patrol {
+            route: vec![MoveDirection::North.into(), MoveDirection::South.into()],
+            next: 0,
+            heading: None,
+        };
+
+        assert_eq!(step(&mut patrol, &world, &npc), None);
+
+        gate.borrow_mut().set_state(DoorState::Open);
+        assert_eq!(step(&mut patrol, &world, &npc), Some(yard.get_id()));
+        // The walk did not happen, so the patrol tries the same step again.
+        assert_eq!(step(&mut patrol, &world, &npc), Some(yard.get_id()));
+
+        world.teleport_character(npc_id, &yard);
+        assert_eq!(step(&mut patrol, &world, &npc), Some(hall.get_id()));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::game::PlayerId;
+use std::collections::HashMap;
+
+/// Collects the text addressed to every player until the transport takes it away.
+pub struct Output {
+    buffers: HashMap<PlayerId, String>,
+    logging: bool,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self {
+            buffers: HashMap::new(),
+            logging: false,
+        }
+    }
+
+    /// Mirrors every message to stdout, which is handy to debug a server. It shows what the
+    /// players type in and read, so it is off unless asked for.
+    pub fn set_logging(&mut self, logging: bool) {
+        self.logging = logging;
+    }
+
+    pub fn send(&mut self, to: PlayerId, text: &str) {
+        if self.logging {
+            println!("${:?} {}", to, text);
+        }
+
+        let buffer = self.buffers.entry(to).or_default();
+        buffer.push_str(text);
+        buffer.push('\n');
+    }
+
+    /// Takes everything sent to the player so far.
+    pub fn take(&mut self, player_id: PlayerId) -> Option<String> {
+        self.buffers.remove(&player_id)
+    }
+
+    pub fn discard(&mut self, player_id: PlayerId) {
+        self.buffers.remove(&player_id);
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{Room, RoomId, World, WorldId};
+    use crate::game::{Game, Player};
+    use crate::socium::Character;
+    use std::rc::Rc;
+
+    fn spawn(game: &mut Game, world_id: WorldId, name: &str) -> PlayerId {
+        let player_id = game.allocate_player_id();
+        game.add_player(Player::new(player_id)).unwrap();
+
+        let world = game.get_world(world_id).unwrap().clone();
+        let mut char = Character::new(world.borrow_mut().allocate_character_id());
+        char.set_name(String::from(name));
+        game.spawn_player_character(world_id, player_id, char)
+            .unwrap();
+        player_id
+    }
+
+    #[test]
+    fn messages_reach_the_actor_the_room_and_the_world() {
+        let mut game = Game::new();
+        let world_id = game
+            .add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+        let other_world_id = game
+            .add_world(World::new(WorldId::from(2), String::from("Other")))
+            .unwrap();
+        for id in [world_id, other_world_id] {
+            let world = game.get_world(id).unwrap().clone();
+            for room_id in [1, 2] {
+                let room = Rc::new(Room::new_in_void(RoomId::from(room_id), world.clone()));
+                world.borrow_mut().add_room(room);
+            }
+        }
+
+        let actor = spawn(&mut game, world_id, "Alice");
+        let witness = spawn(&mut game, world_id, "Bob");
+        let world = game.get_world(world_id).unwrap().clone();
+        world.borrow_mut().set_spawn_room(RoomId::from(2));
+        let neighbour = spawn(&mut game, world_id, "Carol");
+        let stranger = spawn(&mut game, other_world_id, "Dave");
+        for player_id in [actor, witness, neighbour, stranger] {
+            game.take_output(player_id);
+        }
+
+        game.send_to_player(actor, "You wave.");
+        let room = world.borrow().get_room(RoomId::from(1)).unwrap().clone();
+        game.send_to_room(&room, Some(actor), "Alice waves.");
+        game.send_to_world(world_id, "The bell rings.");
+
+        assert_eq!(
+            game.take_output(actor).as_deref(),
+            Some("You wave.\nThe bell rings.\n")
+        );
+        assert_eq!(
+            game.take_output(witness).as_deref(),
+            Some("Alice waves.\nThe bell rings.\n")
+        );
+        assert_eq!(
+            game.take_output(neighbour).as_deref(),
+            Some("The bell rings.\n")
+        );
+        assert_eq!(game.take_output(stranger), None);
+        assert_eq!(game.take_output(actor), None);
+    }
+}
@@ -0,0 +1,715 @@
+use crate::action::{GameAction, render_room};
+use crate::area::{Room, RoomId, World, WorldRef};
+use crate::game::{Game, GameEvent};
+use crate::item::{Item, ItemId, ItemLocation};
+use crate::socium::{CharacterId, Vital};
+use rhai::{AST, Array, Dynamic, Engine, EvalAltResult, Scope};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How many operations a single run of a script may take.
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// How long a single run of a script may take, however few operations it needs.
+const MAX_DURATION: Duration = Duration::from_millis(10);
+
+/// How many operations pass between two looks at the clock.
+const CLOCK_CHECK_OPERATIONS: u64 = 256;
+
+/// How many NPCs and items a single run of a script may spawn.
+const MAX_SPAWNS: usize = 4;
+
+/// How crowded a room may get before scripts stop spawning NPCs or items in it.
+const MAX_ROOM_OCCUPANTS: usize = 20;
+const MAX_ROOM_ITEMS: usize = 50;
+
+const MAX_CALL_LEVELS: usize = 16;
+const MAX_STRING_SIZE: usize = 4096;
+const MAX_COLLECTION_SIZE: usize = 256;
+
+/// What a script waits for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum ScriptTrigger {
+    /// A character walks into the room.
+    Enter,
+    /// Someone speaks in the room.
+    Speech,
+    /// Some time passes.
+    Tick,
+    /// A character picks up an item.
+    Take,
+}
+
+/// A script attached to a room, an item or an NPC template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptDef {
+    pub trigger: ScriptTrigger,
+    pub code: String,
+}
+
+/// What a script belongs to, for the messages about it.
+#[derive(Debug, Clone, Copy)]
+pub enum ScriptOwner {
+    Room(RoomId),
+    Item(ItemId),
+    Npc(CharacterId),
+    NpcTemplate(u32),
+}
+
+impl fmt::Display for ScriptOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptOwner::Room(id) => write!(f, "room {}", id),
+            ScriptOwner::Item(id) => write!(f, "item {}", id),
+            ScriptOwner::Npc(id) => write!(f, "NPC {}", id),
+            ScriptOwner::NpcTemplate(id) => write!(f, "NPC template {}", id),
+        }
+    }
+}
+
+/// Something which happened in a room and may wake up the scripts around.
+#[derive(Debug, Clone)]
+pub struct ScriptEvent {
+    pub trigger: ScriptTrigger,
+    pub room: Rc<Room>,
+    /// The character who did it, if anyone.
+    pub actor: Option<CharacterId>,
+    /// What was said.
+    pub text: Option<String>,
+    /// The item the event is about.
+    pub item: Option<ItemId>,
+}
+
+impl ScriptEvent {
+    pub fn new(trigger: ScriptTrigger, room: Rc<Room>) -> Self {
+        Self {
+            trigger,
+            room,
+            actor: None,
+            text: None,
+            item: None,
+        }
+    }
+
+    pub fn with_actor(mut self, actor: CharacterId) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = Some(text);
+        self
+    }
+
+    pub fn with_item(mut self, item: ItemId) -> Self {
+        self.item = Some(item);
+        self
+    }
+}
+
+/// A change a script asks for. Scripts only read the world while they run; their changes are
+/// applied once they finish, so a failing script changes nothing.
+#[derive(Debug, Clone)]
+pub enum ScriptCommand {
+    SendToCharacter(CharacterId, String),
+    SendToRoom(RoomId, String),
+    Say(CharacterId, String),
+    MoveCharacter(CharacterId, RoomId),
+    SpawnNpc(u32, RoomId),
+    SpawnItem {
+        room: RoomId,
+        keyword: String,
+        short_description: String,
+    },
+}
+
+#[derive(Default)]
+struct ScriptContext {
+    world: Option<WorldRef>,
+    commands: Vec<ScriptCommand>,
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Engine
+// ----------------------------------------------------------------------------------------------------
+
+/// Runs the scripts of the builders in a sandbox. A script sees the world through a few query
+/// functions and cannot take more than a bounded number of operations and time.
+pub struct ScriptEngine {
+    engine: Engine,
+    context: Rc<RefCell<ScriptContext>>,
+    deadline: Rc<Cell<Instant>>,
+    /// The operations the running script took so far.
+    operations: Rc<Cell<u64>>,
+    budget: Option<ScriptBudget>,
+    compiled: HashMap<String, Option<Rc<AST>>>,
+}
+
+/// What a batch of scripts may take together, like all the scripts of a tick.
+struct ScriptBudget {
+    deadline: Instant,
+    operations: u64,
+    exhausted: bool,
+}
+
+impl ScriptEngine {
+    pub fn new() -> Self {
+        let context = Rc::new(RefCell::new(ScriptContext::default()));
+        let deadline = Rc::new(Cell::new(Instant::now()));
+
+        let mut engine = build_engine();
+
+        let operations = Rc::new(Cell::new(0));
+        let progress_deadline = deadline.clone();
+        let progress_operations = operations.clone();
+        engine.on_progress(move |operations| {
+            progress_operations.set(operations);
+            if operations % CLOCK_CHECK_OPERATIONS == 0 && Instant::now() > progress_deadline.get()
+            {
+                Some(Dynamic::from("the script took too long"))
+            } else {
+                None
+            }
+        });
+
+        register_commands(&mut engine, &context);
+        register_queries(&mut engine, &context);
+
+        Self {
+            engine,
+            context,
+            deadline,
+            operations,
+            budget: None,
+            compiled: HashMap::new(),
+        }
+    }
+
+    /// Shares the time and the operations among the scripts run until the budget is unset. Once
+    /// it is spent, the scripts are skipped.
+    pub fn set_budget(&mut self, duration: Duration, operations: u64) {
+        self.budget = Some(ScriptBudget {
+            deadline: Instant::now() + duration,
+            operations,
+            exhausted: false,
+        });
+    }
+
+    pub fn unset_budget(&mut self) {
+        self.budget = None;
+    }
+
+    /// Runs the script for the event and returns the changes it asks for. Scripts which do not
+    /// compile are reported once and then skipped.
+    pub fn run(
+        &mut self,
+        world: &WorldRef,
+        owner: ScriptOwner,
+        code: &str,
+        event: &ScriptEvent,
+    ) -> Vec<ScriptCommand> {
+        let Some(ast) = self.compile(owner, code) else {
+            return Vec::new();
+        };
+
+        let mut deadline = Instant::now() + MAX_DURATION;
+        let mut max_operations = MAX_OPERATIONS;
+        if let Some(budget) = &mut self.budget {
+            if budget.operations == 0 || Instant::now() >= budget.deadline {
+                if !budget.exhausted {
+                    budget.exhausted = true;
+                    eprintln!(">>> Scripts ran out of budget, the rest are skipped");
+                }
+                return Vec::new();
+            }
+            deadline = deadline.min(budget.deadline);
+            max_operations = max_operations.min(budget.operations);
+        }
+        self.engine.set_max_operations(max_operations);
+
+        let mut scope = Scope::new();
+        scope.push_constant("room", id_value(event.room.get_id()));
+        scope.push_constant("actor", event.actor.map_or(Dynamic::UNIT, id_value));
+        scope.push_constant(
+            "text",
+            event.text.clone().map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        let item = match owner {
+            ScriptOwner::Item(id) => Some(id),
+            _ => event.item,
+        };
+        scope.push_constant("item", item.map_or(Dynamic::UNIT, id_value));
+        let me = match owner {
+            ScriptOwner::Npc(id) => id_value(id),
+            _ => Dynamic::UNIT,
+        };
+        scope.push_constant("me", me);
+
+        self.context.borrow_mut().world = Some(world.clone());
+        self.deadline.set(deadline);
+        self.operations.set(0);
+
+        let result = self.engine.run_ast_with_scope(&mut scope, &ast);
+
+        if let Some(budget) = &mut self.budget {
+            budget.operations = budget.operations.saturating_sub(self.operations.get());
+        }
+
+        let mut context = self.context.borrow_mut();
+        context.world = None;
+        let commands = std::mem::take(&mut context.commands);
+
+        match result {
+            Ok(()) => commands,
+            Err(error) => {
+                eprintln!(">>> Script of {} failed: {error}", owner);
+                Vec::new()
+            }
+        }
+    }
+
+    fn compile(&mut self, owner: ScriptOwner, code: &str) -> Option<Rc<AST>> {
+        if let Some(compiled) = self.compiled.get(code) {
+            return compiled.clone();
+        }
+
+        let compiled = match self.engine.compile(code) {
+            Ok(ast) => Some(Rc::new(ast)),
+            Err(error) => {
+                eprintln!(">>> Script of {} does not compile: {error}", owner);
+                None
+            }
+        };
+        self.compiled.insert(code.to_string(), compiled.clone());
+
+        compiled
+    }
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The engine with the limits of the sandbox, before the game functions are registered.
+fn build_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+    engine.disable_symbol("eval");
+    engine.on_print(|text| eprintln!(">>> Script: {text}"));
+    engine.on_debug(|text, _, _| eprintln!(">>> Script: {text}"));
+
+    engine
+}
+
+/// Parses the script without running it, with the same restrictions it will run with.
+pub fn check_syntax(code: &str) -> Result<(), String> {
+    build_engine()
+        .compile(code)
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+fn id_value(id: impl Into<u32>) -> Dynamic {
+    Dynamic::from(i64::from(id.into()))
+}
+
+fn to_id<T: From<u32>>(value: i64) -> Option<T> {
+    u32::try_from(value).ok().map(T::from)
+}
+
+fn register_commands(engine: &mut Engine, context: &Rc<RefCell<ScriptContext>>) {
+    let ctx = context.clone();
+    engine.register_fn("send", move |char: i64, text: &str| {
+        let command = to_id(char).map(|id| ScriptCommand::SendToCharacter(id, text.to_string()));
+        push_command(&ctx, command);
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("send_room", move |room: i64, text: &str| {
+        let command = to_id(room).map(|id| ScriptCommand::SendToRoom(id, text.to_string()));
+        push_command(&ctx, command);
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("say", move |char: i64, text: &str| {
+        let command = to_id(char).map(|id| ScriptCommand::Say(id, text.to_string()));
+        push_command(&ctx, command);
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("move_char", move |char: i64, room: i64| {
+        let command = to_id(char)
+            .zip(to_id(room))
+            .map(|(char, room)| ScriptCommand::MoveCharacter(char, room));
+        push_command(&ctx, command);
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("spawn_npc", move |template: i64, room: i64| {
+        let command = to_id(template)
+            .zip(to_id(room))
+            .map(|(template, room)| ScriptCommand::SpawnNpc(template, room));
+        push_spawn(&ctx, command)
+    });
+
+    let ctx = context.clone();
+    engine.register_fn(
+        "spawn_item",
+        move |room: i64, keyword: &str, short_description: &str| {
+            let command = to_id(room).map(|room| ScriptCommand::SpawnItem {
+                room,
+                keyword: keyword.to_string(),
+                short_description: short_description.to_string(),
+            });
+            push_spawn(&ctx, command)
+        },
+    );
+}
+
+/// Every query answers `()` about things which do not exist.
+fn register_queries(engine: &mut Engine, context: &Rc<RefCell<ScriptContext>>) {
+    let ctx = context.clone();
+    engine.register_fn("char_name", move |char: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let char = world.get_character(to_id(char)?)?;
+            Some(Dynamic::from(char.borrow().get_name()))
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("char_room", move |char: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let char = world.get_character(to_id(char)?)?;
+            let room = char.borrow().get_current_room().clone()?;
+            Some(id_value(room.get_id()))
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("is_player", move |char: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let char = world.get_character(to_id(char)?)?;
+            Some(Dynamic::from(char.borrow().get_owner().is_some()))
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("hit_points", move |char: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let char = world.get_character(to_id(char)?)?;
+            let current = char.borrow().get_vital(Vital::HitPoints).get_current();
+            Some(Dynamic::from(i64::from(current)))
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("chars_in_room", move |room: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let room = world.get_room(to_id(room)?)?;
+            let ids: Array = room.get_occupants().into_iter().map(id_value).collect();
+            Some(Dynamic::from(ids))
+        })
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("room_title", move |room: i64| -> Dynamic {
+        with_world(&ctx, |world| {
+            let room = world.get_room(to_id(room)?)?;
+            Some(Dynamic::from(room.get_title().to_string()))
+        })
+    });
+}
+
+fn push_command(context: &RefCell<ScriptContext>, command: Option<ScriptCommand>) {
+    if let Some(command) = command {
+        context.borrow_mut().commands.push(command);
+    }
+}
+
+/// Fails the script when it spawns too much, which throws away everything it asked for.
+fn push_spawn(
+    context: &RefCell<ScriptContext>,
+    command: Option<ScriptCommand>,
+) -> Result<(), Box<EvalAltResult>> {
+    let spawns = context
+        .borrow()
+        .commands
+        .iter()
+        .filter(|command| {
+            matches!(
+                command,
+                ScriptCommand::SpawnNpc(..) | ScriptCommand::SpawnItem { .. }
+            )
+        })
+        .count();
+    if command.is_some() && spawns >= MAX_SPAWNS {
+        return Err(format!("a script may spawn at most {} things at once", MAX_SPAWNS).into());
+    }
+
+    push_command(context, command);
+    Ok(())
+}
+
+fn with_world(
+    context: &RefCell<ScriptContext>,
+    query: impl FnOnce(&World) -> Option<Dynamic>,
+) -> Dynamic {
+    let context = context.borrow();
+    let Some(world) = &context.world else {
+        return Dynamic::UNIT;
+    };
+
+    query(&world.borrow()).unwrap_or(Dynamic::UNIT)
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Events
+// ----------------------------------------------------------------------------------------------------
+
+/// Wakes up the scripts waiting for what happened.
+pub(crate) fn on_game_event(game: &mut Game, event: &GameEvent) {
+    let event = match event {
+        GameEvent::CharacterMoved { who, to, .. } => {
+            ScriptEvent::new(ScriptTrigger::Enter, to.clone()).with_actor(who.borrow().get_id())
+        }
+        GameEvent::SaidInRoom { who, room, text } => {
+            ScriptEvent::new(ScriptTrigger::Speech, room.clone())
+                .with_actor(who.borrow().get_id())
+                .with_text(text.clone())
+        }
+        GameEvent::ItemTaken { who, room, item } => {
+            ScriptEvent::new(ScriptTrigger::Take, room.clone())
+                .with_actor(who.borrow().get_id())
+                .with_item(*item)
+        }
+        _ => return,
+    };
+
+    fire_event(game, event);
+}
+
+/// Runs the scripts waiting for the event: the ones of the room, of the NPCs in it other than the
+/// actor, of the items lying there and of the item the event is about.
+pub(crate) fn fire_event(game: &mut Game, event: ScriptEvent) {
+    let world = event.room.get_world().clone();
+
+    let scripts: Vec<(ScriptOwner, String)> = {
+        let world = world.borrow();
+        let mut scripts = Vec::new();
+        let mut collect = |owner: ScriptOwner, defs: &[ScriptDef]| {
+            for def in defs.iter().filter(|def| def.trigger == event.trigger) {
+                scripts.push((owner, def.code.clone()));
+            }
+        };
+
+        collect(
+            ScriptOwner::Room(event.room.get_id()),
+            event.room.get_scripts(),
+        );
+
+        for char_id in event.room.get_occupants() {
+            if Some(char_id) == event.actor {
+                continue;
+            }
+            let template = world
+                .get_character(char_id)
+                .and_then(|char| char.borrow().get_template())
+                .and_then(|template| world.get_npc_template(template));
+            if let Some(template) = template {
+                collect(ScriptOwner::Npc(char_id), &template.scripts);
+            }
+        }
+
+        let mut item_ids = event.room.get_items();
+        if let Some(item_id) = event.item
+            && !item_ids.contains(&item_id)
+        {
+            item_ids.push(item_id);
+        }
+        for item_id in item_ids {
+            if let Some(item) = world.get_item(item_id) {
+                collect(ScriptOwner::Item(item_id), item.borrow().get_scripts());
+            }
+        }
+
+        scripts
+    };
+
+    for (owner, code) in scripts {
+        let commands = game.get_scripts_mut().run(&world, owner, &code, &event);
+        for command in commands {
+            apply_command(game, &world, command);
+        }
+    }
+}
+
+fn apply_command(game: &mut Game, world: &WorldRef, command: ScriptCommand) {
+    match command {
+        ScriptCommand::SendToCharacter(char_id, text) => {
+            let owner_id = world.borrow().get_character(char_id).and_then(|char| {
+                char.borrow()
+                    .get_owner()
+                    .as_ref()
+                    .map(|p| p.borrow().get_id())
+            });
+            if let Some(owner_id) = owner_id {
+                game.send_to_player(owner_id, &text);
+            }
+        }
+        ScriptCommand::SendToRoom(room_id, text) => {
+            let room = world.borrow().get_room(room_id).cloned();
+            if let Some(room) = room {
+                game.send_to_room(&room, None, &text);
+            }
+        }
+        ScriptCommand::Say(char_id, text) => {
+            let char = world.borrow().get_character(char_id).cloned();
+            if let Some(who) = char {
+                game.queue_action(GameAction::Say { who, text });
+            }
+        }
+        ScriptCommand::MoveCharacter(char_id, room_id) => {
+            let room = world.borrow().get_room(room_id).cloned();
+            let char = world.borrow().get_character(char_id).cloned();
+            let (Some(room), Some(char)) = (room, char) else {
+                return;
+            };
+            let from = char.borrow().get_current_room().clone();
+            if !world.borrow().teleport_character(char_id, &room) {
+                return;
+            }
+
+            let owner_id = char
+                .borrow()
+                .get_owner()
+                .as_ref()
+                .map(|p| p.borrow().get_id());
+            if let Some(owner_id) = owner_id {
+                game.send_to_player(owner_id, &render_room(&room, char_id, false));
+            }
+
+            if let Some(from) = from {
+                game.publish(GameEvent::CharacterMoved {
+                    who: char,
+                    from,
+                    to: room,
+                });
+            }
+        }
+        ScriptCommand::SpawnNpc(template_id, room_id) => {
+            let room = world.borrow().get_room(room_id).cloned();
+            let Some(room) = room else {
+                return;
+            };
+            if room.get_occupants().len() >= MAX_ROOM_OCCUPANTS {
+                eprintln!(
+                    ">>> Room {} is too crowded for a script to spawn an NPC",
+                    room_id
+                );
+                return;
+            }
+            let spawned = world.borrow_mut().spawn_npc(template_id, &room);
+            match spawned {
+                Ok(char_id) => {
+                    let char = world.borrow().get_character(char_id).cloned();
+                    if let Some(who) = char {
+                        game.publish(GameEvent::CharacterSpawned { who, room });
+                    }
+                }
+                Err(error) => eprintln!(">>> A script cannot spawn an NPC: {error}"),
+            }
+        }
+        ScriptCommand::SpawnItem {
+            room,
+            keyword,
+            short_description,
+        } => {
+            let Some(item_count) = world.borrow().get_room(room).map(|r| r.get_items().len())
+            else {
+                return;
+            };
+            if item_count >= MAX_ROOM_ITEMS {
+                eprintln!(
+                    ">>> Room {} is too cluttered for a script to spawn an item",
+                    room
+                );
+                return;
+            }
+
+            let mut world = world.borrow_mut();
+            let item = Item::new(world.allocate_item_id(), vec![keyword], short_description);
+            if let Err(error) = world.add_item(item, ItemLocation::Room(room)) {
+                eprintln!(">>> A script cannot spawn an item: {error}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::WorldId;
+    use crate::utils::BuildRef;
+
+    fn tick_in_room() -> (WorldRef, ScriptEvent) {
+        let world = World::new(WorldId::from(1), String::from("Test")).build_ref();
+        let room = Rc::new(Room::new_in_void(RoomId::from(1), world.clone()));
+        world.borrow_mut().add_room(room.clone());
+
+        (world, ScriptEvent::new(ScriptTrigger::Tick, room))
+    }
+
+    fn owner() -> ScriptOwner {
+        ScriptOwner::Room(RoomId::from(1))
+    }
+
+    #[test]
+    fn script_spawning_too_much_changes_nothing() {
+        let (world, event) = tick_in_room();
+        let mut engine = ScriptEngine::new();
+
+        let spawn = |count: usize| {
+            format!("for i in 0..{count} {{ spawn_item(room, \"rat\", \"a rat\"); }}")
+        };
+        let commands = engine.run(&world, owner(), &spawn(MAX_SPAWNS), &event);
+        assert_eq!(commands.len(), MAX_SPAWNS);
+        assert!(
+            engine
+                .run(&world, owner(), &spawn(MAX_SPAWNS + 1), &event)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn spent_budget_skips_the_scripts() {
+        let (world, event) = tick_in_room();
+        let mut engine = ScriptEngine::new();
+        let cheap = "send_room(room, \"drip\");";
+        let endless = "loop { send_room(room, \"drip\"); }";
+
+        engine.set_budget(Duration::from_secs(60), 1_000);
+        assert_eq!(engine.run(&world, owner(), cheap, &event).len(), 1);
+        assert!(engine.run(&world, owner(), endless, &event).is_empty());
+        assert!(engine.run(&world, owner(), cheap, &event).is_empty());
+
+        engine.unset_budget();
+        assert_eq!(engine.run(&world, owner(), cheap, &event).len(), 1);
+    }
+
+    #[test]
+    fn syntax_check_refuses_what_the_sandbox_forbids() {
+        assert!(check_syntax(r#"send(actor, "Hello!");"#).is_ok());
+        assert!(check_syntax("let x = ;").is_err());
+        assert!(check_syntax(r#"eval("40 + 2")"#).is_err());
+    }
+}
@@ -0,0 +1,530 @@
+use crate::account::{self, AccountStoreRef, Login};
+use crate::action::CharAction;
+use crate::area::WorldId;
+use crate::creation::CreationRules;
+use crate::game::{Game, GameEvent, Player, PlayerId};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::rc::Rc;
+
+const MAX_LINE_LENGTH: usize = 1024;
+const READ_CHUNK_SIZE: usize = 512;
+
+/// How many lines of a client run in one pulse. The rest wait for the next pulses, so that
+/// pasting a pile of commands does not hold up everybody else.
+const MAX_LINES_PER_PULSE: usize = 4;
+
+/// How much unread input a session may hold. The client is not read from while it is full.
+const MAX_INPUT_SIZE: usize = 64 * 1024;
+
+/// How much unsent output a session may pile up before it is dropped as not reading it.
+const MAX_OUTPUT_SIZE: usize = 256 * 1024;
+
+// Telnet protocol bytes. The only option we negotiate is the echo, to hide passwords; the
+// options the client sends are stripped from the input.
+const TELNET_IAC: u8 = 255;
+const TELNET_SB: u8 = 250;
+const TELNET_SE: u8 = 240;
+const TELNET_WILL: u8 = 251;
+const TELNET_WONT: u8 = 252;
+const TELNET_DONT: u8 = 254;
+const TELNET_ECHO: u8 = 1;
+
+/// Accepts telnet connections and binds each of them to its own `Player` of the game, which
+/// logs into an account and picks a character to play.
+pub struct Server {
+    listener: TcpListener,
+    start_world: WorldId,
+    sessions: HashMap<PlayerId, Session>,
+    accounts: AccountStoreRef,
+    creation: Rc<CreationRules>,
+}
+
+impl Server {
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        start_world: WorldId,
+        accounts: AccountStoreRef,
+        creation: Rc<CreationRules>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            listener,
+            start_world,
+            sessions: HashMap::new(),
+            accounts,
+            creation,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn accept_connections(&mut self, game: &mut Game) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => self.open_session(game, stream, address),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    eprintln!("Failed to accept a connection: {error}");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Reads whatever the clients sent since the last pulse and executes a few complete lines
+    /// of each. Lines of the players who have not chosen a character yet go to their login
+    /// instead.
+    pub fn process_input(&mut self, game: &mut Game) {
+        let input: Vec<(PlayerId, Vec<String>)> = self
+            .sessions
+            .iter_mut()
+            .map(|(player_id, session)| (*player_id, session.read_lines()))
+            .collect();
+
+        for (player_id, lines) in input {
+            for line in lines {
+                self.process_line(game, player_id, &line);
+            }
+        }
+
+        self.close_dead_sessions(game);
+    }
+
+    fn process_line(&mut self, game: &mut Game, player_id: PlayerId, line: &str) {
+        let online: HashSet<String> = self
+            .sessions
+            .iter()
+            .filter(|(id, _)| **id != player_id)
+            .filter_map(|(_, session)| session.login.get_account())
+            .map(|account| account.to_lowercase())
+            .collect();
+
+        let Some(session) = self.sessions.get_mut(&player_id) else {
+            return;
+        };
+
+        // The client did not echo the end of the password line either.
+        if session.echo_off {
+            session.write("\n");
+        }
+
+        if session.login.is_playing() {
+            let command = game.parse_command(player_id, line);
+            execute_command(game, player_id, command.as_ref());
+        } else {
+            session.login.handle(
+                game,
+                &mut self.accounts.borrow_mut(),
+                &online,
+                player_id,
+                line,
+            );
+        }
+    }
+
+    pub fn flush_output(&mut self, game: &mut Game) {
+        for player_id in game.take_ended_sessions() {
+            if let Some(session) = self.sessions.get_mut(&player_id) {
+                session.closing = true;
+            }
+        }
+
+        for (player_id, session) in self.sessions.iter_mut() {
+            session.update_echo();
+
+            if let Some(text) = game.take_output(*player_id) {
+                session.write(&text);
+            }
+
+            session.flush();
+
+            if session.closing && session.output.is_empty() {
+                session.closed = true;
+            }
+        }
+
+        self.close_dead_sessions(game);
+    }
+
+    fn open_session(&mut self, game: &mut Game, stream: TcpStream, address: SocketAddr) {
+        if let Err(error) = stream.set_nonblocking(true) {
+            eprintln!("Failed to set up a connection from {address}: {error}");
+            return;
+        }
+
+        let player_id = game.allocate_player_id();
+        if let Err(error) = game.add_player(Player::new(player_id)) {
+            eprintln!("Cannot let in a connection from {address}: {error}");
+            let _ = (&stream).write_all(b"The game cannot let you in now, sorry.\r\n");
+            return;
+        }
+
+        println!(">>> {:?} connected from {address}", player_id);
+        game.publish(GameEvent::PlayerConnected(player_id));
+
+        let login = Login::new(self.start_world, self.creation.clone());
+        login.greet(game, player_id);
+        self.sessions
+            .insert(player_id, Session::new(stream, address, login));
+    }
+
+    fn close_dead_sessions(&mut self, game: &mut Game) {
+        let dead: Vec<PlayerId> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| s.closed)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for player_id in dead {
+            if let Some(session) = self.sessions.remove(&player_id) {
+                println!(">>> {:?} disconnected from {}", player_id, session.address);
+
+                if session.login.is_playing()
+                    && let Some(account) = session.login.get_account()
+                {
+                    let mut accounts = self.accounts.borrow_mut();
+                    account::save_character(game, &mut accounts, account, player_id);
+                }
+            }
+
+            game.disconnect_player(player_id);
+            game.publish(GameEvent::PlayerDisconnected(player_id));
+        }
+    }
+}
+
+struct Session {
+    stream: TcpStream,
+    address: SocketAddr,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    closing: bool,
+    closed: bool,
+    /// Whether the client was asked not to echo what is typed.
+    echo_off: bool,
+    login: Login,
+}
+
+impl Session {
+    fn new(stream: TcpStream, address: SocketAddr, login: Login) -> Self {
+        Self {
+            stream,
+            address,
+            login,
+            input: Vec::new(),
+            output: Vec::new(),
+            closing: false,
+            closed: false,
+            echo_off: false,
+        }
+    }
+
+    /// Asks the client to stop echoing while the login waits for a password and to echo again
+    /// afterwards. By saying that the server will echo, it makes the client stop doing it.
+    fn update_echo(&mut self) {
+        let echo_off = self.login.is_asking_password();
+        if echo_off == self.echo_off {
+            return;
+        }
+
+        let verb = if echo_off { TELNET_WILL } else { TELNET_WONT };
+        self.output
+            .extend_from_slice(&[TELNET_IAC, verb, TELNET_ECHO]);
+        self.echo_off = echo_off;
+    }
+
+    /// Queues the text for sending, converting line ends to the telnet ones.
+    fn write(&mut self, text: &str) {
+        for b in text.bytes() {
+            if b == b'\n' {
+                self.output.push(b'\r');
+            }
+            self.output.push(b);
+        }
+    }
+
+    fn read_lines(&mut self) -> Vec<String> {
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+
+        while self.input.len() < MAX_INPUT_SIZE {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => self.input.extend_from_slice(&chunk[..n]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+
+        while lines.len() < MAX_LINES_PER_PULSE
+            && let Some(end) = self.input.iter().position(|b| *b == b'\n')
+        {
+            let raw: Vec<u8> = self.input.drain(..=end).collect();
+            if end > MAX_LINE_LENGTH {
+                self.write("Your line is too long.\n");
+                continue;
+            }
+            lines.push(decode_line(&raw));
+        }
+
+        // The line still being typed is cut as soon as it is too long, the complete ones which
+        // wait their turn are checked when they are drained.
+        let unfinished_start = self
+            .input
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |end| end + 1);
+        if self.input.len() - unfinished_start > MAX_LINE_LENGTH {
+            self.input.truncate(unfinished_start);
+            self.write("Your line is too long.\n");
+        }
+
+        lines
+    }
+
+    fn flush(&mut self) {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+
+        if self.output.len() > MAX_OUTPUT_SIZE {
+            eprintln!(">>> {} does not read its output, closing it", self.address);
+            self.output.clear();
+            self.closed = true;
+        }
+    }
+}
+
+/// Executes the command and keeps the server running whatever happens to it.
+fn execute_command(game: &mut Game, player_id: PlayerId, command: &dyn CharAction) {
+    if let Err(error) = command.execute(game, player_id) {
+        eprintln!(">>> {:?} failed a command: {error}", player_id);
+        game.send_to_player(player_id, "Something went wrong. Please try again.");
+    }
+}
+
+/// Strips telnet commands and the line terminator from a raw input line.
+fn decode_line(raw: &[u8]) -> String {
+    let mut text = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter().copied();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            TELNET_IAC => match bytes.next() {
+                Some(TELNET_IAC) => text.push(TELNET_IAC),
+                Some(TELNET_SB) => {
+                    // Skip the subnegotiation up to IAC SE.
+                    let mut prev = 0;
+                    for b in bytes.by_ref() {
+                        if prev == TELNET_IAC && b == TELNET_SE {
+                            break;
+                        }
+                        prev = b;
+                    }
+                }
+                Some(TELNET_WILL..=TELNET_DONT) => {
+                    bytes.next();
+                }
+                _ => (),
+            },
+            b'\r' | b'\n' => (),
+            _ => text.push(b),
+        }
+    }
+
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Account, AccountStore};
+    use crate::area::World;
+    use crate::utils::BuildRef;
+    use std::thread;
+    use std::time::Duration;
+
+    fn start() -> (Game, Server, TcpStream) {
+        let mut game = Game::new();
+        game.add_world(World::new(WorldId::from(1), String::from("Test")))
+            .unwrap();
+
+        let mut accounts = AccountStore::new(std::env::temp_dir().join("mud-jam-solo-server.ron"));
+        accounts
+            .add_account(Account::new(String::from("Alice"), "secret"))
+            .unwrap();
+        let creation =
+            ron::from_str(r#"(races: [(name: "Human")], classes: [(name: "Warrior")])"#).unwrap();
+
+        let mut server = Server::bind(
+            "127.0.0.1:0",
+            WorldId::from(1),
+            accounts.build_ref(),
+            Rc::new(creation),
+        )
+        .unwrap();
+        let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client.set_nonblocking(true).unwrap();
+        pump(&mut game, &mut server, &client, |r| {
+            contains(r, b"By what name")
+        });
+
+        (game, server, client)
+    }
+
+    /// Runs pulses until the client got what it waits for, and returns all it got.
+    fn pump(
+        game: &mut Game,
+        server: &mut Server,
+        mut client: &TcpStream,
+        done: impl Fn(&[u8]) -> bool,
+    ) -> Vec<u8> {
+        let mut received = Vec::new();
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+
+        for _ in 0..100 {
+            pulse(game, server);
+
+            while let Ok(n) = client.read(&mut chunk) {
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&chunk[..n]);
+            }
+            if done(&received) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        received
+    }
+
+    fn pulse(game: &mut Game, server: &mut Server) {
+        server.accept_connections(game);
+        server.process_input(game);
+        game.tick();
+        server.flush_output(game);
+    }
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|w| *w == needle)
+            .count()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn client_echo_is_off_only_while_a_password_is_typed() {
+        let (mut game, mut server, mut client) = start();
+        let echo_off = [TELNET_IAC, TELNET_WILL, TELNET_ECHO];
+        let echo_on = [TELNET_IAC, TELNET_WONT, TELNET_ECHO];
+
+        client.write_all(b"alice\r\n").unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| contains(r, &echo_off));
+        assert!(contains(&received, &echo_off));
+        assert!(contains(&received, b"Password:"));
+
+        client.write_all(b"secret\r\n").unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| contains(r, &echo_on));
+        assert!(contains(&received, &echo_on));
+        assert!(contains(&received, b"You have no characters yet."));
+    }
+
+    #[test]
+    fn a_pile_of_lines_runs_a_few_per_pulse() {
+        let (mut game, mut server, mut client) = start();
+        let rules = b"A name is one word";
+
+        client.write_all(&b"x\r\n".repeat(10)).unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| !r.is_empty());
+        assert_eq!(count(&received, rules), MAX_LINES_PER_PULSE);
+
+        let received = pump(&mut game, &mut server, &client, |r| {
+            count(r, rules) >= 10 - MAX_LINES_PER_PULSE
+        });
+        assert_eq!(count(&received, rules), 10 - MAX_LINES_PER_PULSE);
+    }
+
+    #[test]
+    fn overlong_line_is_dropped() {
+        let (mut game, mut server, mut client) = start();
+
+        client.write_all(&[b'a'; MAX_LINE_LENGTH + 1]).unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| !r.is_empty());
+        assert!(contains(&received, b"Your line is too long."));
+
+        client.write_all(b"\r\n").unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| !r.is_empty());
+        assert!(contains(&received, b"A name is one word"));
+    }
+
+    #[test]
+    fn overlong_complete_line_is_dropped() {
+        let (mut game, mut server, mut client) = start();
+        let rules = b"A name is one word";
+
+        let mut input = vec![b'a'; MAX_LINE_LENGTH + 1];
+        input.extend_from_slice(b"\r\nx\r\n");
+        client.write_all(&input).unwrap();
+        let received = pump(&mut game, &mut server, &client, |r| contains(r, rules));
+        assert!(contains(&received, b"Your line is too long."));
+        assert_eq!(count(&received, rules), 1);
+    }
+
+    #[test]
+    fn decoding_strips_telnet_commands_and_line_ends() {
+        assert_eq!(decode_line(b"look\r\n"), "look");
+        assert_eq!(
+            decode_line(&[b'a', TELNET_IAC, TELNET_IAC, b'b', b'\n']),
+            "a\u{fffd}b"
+        );
+        assert_eq!(
+            decode_line(&[TELNET_IAC, TELNET_WILL, 31, b'h', b'i']),
+            "hi"
+        );
+        assert_eq!(
+            decode_line(&[TELNET_IAC, TELNET_DONT, TELNET_ECHO, b'n', b'o']),
+            "no"
+        );
+        assert_eq!(
+            decode_line(&[
+                b's', TELNET_IAC, TELNET_SB, 24, 0, b'x', b't', TELNET_IAC, TELNET_SE, b'a', b'y',
+            ]),
+            "say"
+        );
+    }
+}
@@ -0,0 +1,547 @@
+use crate::area::{RoomId, World, WorldId};
+use crate::error::GameError;
+use crate::game::Game;
+use crate::item::{ItemId, ItemLocation, WearSlot};
+use crate::loader::{ItemDef, RoomDef, WorldDef, WorldDefError, build_rooms};
+use crate::socium::{Character, CharacterId, Stat, Vital, VitalPool};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Everything needed to bring a running game back after a restart.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub worlds: Vec<WorldSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub world: WorldDef,
+    pub characters: Vec<CharacterSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CharacterSnapshot {
+    pub id: u32,
+    pub room: Option<u32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub short_description: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    /// The NPC template of a character without a player.
+    #[serde(default)]
+    pub template: Option<u32>,
+    #[serde(default)]
+    pub race: Option<String>,
+    #[serde(default)]
+    pub class: Option<String>,
+    /// The stats without equipment.
+    #[serde(default)]
+    pub stats: BTreeMap<Stat, i32>,
+    #[serde(default)]
+    pub vitals: BTreeMap<Vital, VitalPool>,
+    #[serde(default)]
+    pub inventory: Vec<ItemDef>,
+    #[serde(default)]
+    pub equipment: BTreeMap<WearSlot, ItemDef>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: Box<ron::error::SpannedError>,
+    },
+    Serialize(ron::Error),
+    InvalidWorld {
+        world: WorldId,
+        problems: Vec<WorldDefError>,
+    },
+    DuplicateWorld(WorldId),
+    DuplicateCharacter(WorldId, CharacterId),
+    UnknownRoom(WorldId, RoomId),
+    Game(GameError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SnapshotError::Parse { path, error } => {
+                write!(f, "cannot parse {}: {}", path.display(), error)
+            }
+            SnapshotError::Serialize(error) => write!(f, "cannot serialize the game: {}", error),
+            SnapshotError::InvalidWorld { world, problems } => {
+                write!(f, "invalid world {:?}:", world)?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+            SnapshotError::DuplicateWorld(id) => write!(f, "world {:?} already exists", id),
+            SnapshotError::DuplicateCharacter(world, id) => {
+                write!(f, "character {} already exists in world {:?}", id, world)
+            }
+            SnapshotError::UnknownRoom(world, id) => {
+                write!(f, "no room {} in world {:?}", id, world)
+            }
+            SnapshotError::Game(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<GameError> for SnapshotError {
+    fn from(error: GameError) -> Self {
+        SnapshotError::Game(error)
+    }
+}
+
+/// Captures the state of every world of the game. Players and their characters are left out:
+/// the characters are saved with their accounts and the players log in again after a restart.
+pub fn capture(game: &Game) -> GameSnapshot {
+    let mut worlds: Vec<WorldSnapshot> = game
+        .get_worlds()
+        .map(|world| {
+            let world = world.borrow();
+
+            let rooms: Vec<RoomDef> = world
+                .get_rooms()
+                .iter()
+                .map(|room| RoomDef::from_room(room))
+                .collect();
+
+            // Follow the occupant lists so that the restored rooms list people in the same order.
+            let mut characters: Vec<CharacterSnapshot> = Vec::new();
+            for room in world.get_rooms() {
+                for char_id in room.get_occupants() {
+                    if is_played(&world, char_id) {
+                        continue;
+                    }
+                    let room_id = Some(room.get_id().into());
+                    characters.extend(capture_character(&world, char_id, room_id));
+                }
+            }
+
+            let placed: HashSet<u32> = characters.iter().map(|c| c.id).collect();
+            let mut roomless: Vec<u32> = world
+                .get_characters()
+                .filter(|c| c.borrow().get_owner().is_none())
+                .map(|c| u32::from(c.borrow().get_id()))
+                .filter(|id| !placed.contains(id))
+                .collect();
+            roomless.sort();
+            characters.extend(
+                roomless
+                    .into_iter()
+                    .filter_map(|id| capture_character(&world, CharacterId::from(id), None)),
+            );
+
+            WorldSnapshot {
+                world: WorldDef {
+                    id: world.get_id().into(),
+                    name: world.get_name().to_string(),
+                    spawn: world.get_spawn_room_id().map(u32::from),
+                    rooms,
+                    npc_templates: world.get_npc_templates().cloned().collect(),
+                },
+                characters,
+            }
+        })
+        .collect();
+    worlds.sort_by_key(|w| w.world.id);
+
+    GameSnapshot { worlds }
+}
+
+fn is_played(world: &World, char_id: CharacterId) -> bool {
+    world
+        .get_character(char_id)
+        .is_some_and(|char| char.borrow().get_owner().is_some())
+}
+
+pub(crate) fn capture_character(
+    world: &World,
+    char_id: CharacterId,
+    room: Option<u32>,
+) -> Option<CharacterSnapshot> {
+    let char = world.get_character(char_id)?.borrow();
+
+    let inventory = char
+        .get_inventory()
+        .iter()
+        .filter_map(|id| world.get_item(*id))
+        .map(|item| ItemDef::from_item(world, &item.borrow()))
+        .collect();
+    let equipment = char
+        .get_equipment()
+        .iter()
+        .filter_map(|(slot, id)| Some((*slot, world.get_item(*id)?)))
+        .map(|(slot, item)| (slot, ItemDef::from_item(world, &item.borrow())))
+        .collect();
+
+    Some(CharacterSnapshot {
+        id: char_id.into(),
+        room,
+        name: char.has_name().then(|| char.get_name()),
+        short_description: char.get_short_description().to_string(),
+        keywords: char.get_keywords().to_vec(),
+        description: char.get_description().to_string(),
+        template: char.get_template(),
+        race: char.get_race().map(str::to_string),
+        class: char.get_class().map(str::to_string),
+        stats: char.get_base_stats().iter().collect(),
+        vitals: Vital::ALL
+            .iter()
+            .map(|vital| (*vital, char.get_vital(*vital)))
+            .collect(),
+        inventory,
+        equipment,
+    })
+}
+
+/// Rebuilds the captured worlds inside the game.
+pub fn restore(game: &mut Game, snapshot: &GameSnapshot) -> Result<Vec<WorldId>, SnapshotError> {
+    check(game, snapshot)?;
+
+    let mut world_ids = Vec::new();
+
+    for world_snapshot in &snapshot.worlds {
+        let def = &world_snapshot.world;
+        let world_id = WorldId::from(def.id);
+
+        game.add_world(World::new(world_id, def.name.clone()))?;
+
+        let world = game.require_world(world_id)?.clone();
+        build_rooms(&world, &def.rooms)?;
+        if let Some(spawn) = def.spawn {
+            world.borrow_mut().set_spawn_room(RoomId::from(spawn));
+        }
+
+        let mut world_mut = world.borrow_mut();
+        for template in &def.npc_templates {
+            world_mut.add_npc_template(template.clone())?;
+        }
+
+        for char_snapshot in &world_snapshot.characters {
+            let char_id = CharacterId::from(char_snapshot.id);
+            build_character(&mut world_mut, char_id, char_snapshot)?;
+
+            if let Some(room_id) = char_snapshot.room {
+                let room_id = RoomId::from(room_id);
+                let room = world_mut
+                    .get_room(room_id)
+                    .ok_or(GameError::UnknownRoom(room_id))?
+                    .clone();
+                world_mut.teleport_character(char_id, &room);
+            }
+        }
+
+        world_ids.push(world_id);
+    }
+
+    Ok(world_ids)
+}
+
+/// Adds the captured character to the world under the id, with everything they carry, but
+/// does not put them in a room. Items whose ids are taken get new ones.
+pub(crate) fn build_character(
+    world: &mut World,
+    id: CharacterId,
+    snapshot: &CharacterSnapshot,
+) -> Result<CharacterId, GameError> {
+    let mut char = Character::new(id);
+    if let Some(name) = &snapshot.name {
+        char.set_name(name.clone());
+    }
+    char.set_short_description(snapshot.short_description.clone());
+    char.set_keywords(snapshot.keywords.clone());
+    char.set_description(snapshot.description.clone());
+    if let Some(race) = &snapshot.race {
+        char.set_race(race.clone());
+    }
+    if let Some(class) = &snapshot.class {
+        char.set_class(class.clone());
+    }
+    for (stat, value) in &snapshot.stats {
+        char.set_base_stat(*stat, *value);
+    }
+    for (vital, pool) in &snapshot.vitals {
+        *char.get_vital_mut(*vital) = *pool;
+    }
+    let char_id = world.add_character(char)?;
+    if let Some(template) = snapshot.template {
+        world.make_npc(char_id, template)?;
+    }
+
+    for item in &snapshot.inventory {
+        item.with_free_ids(world)
+            .build(world, ItemLocation::Character(char_id))?;
+    }
+    for (slot, item) in &snapshot.equipment {
+        item.with_free_ids(world)
+            .build(world, ItemLocation::Equipped(char_id, *slot))?;
+    }
+
+    Ok(char_id)
+}
+
+/// Finds the first problem which would break the restore before anything is changed.
+fn check(game: &Game, snapshot: &GameSnapshot) -> Result<(), SnapshotError> {
+    let mut char_ids = HashSet::new();
+
+    for world_snapshot in &snapshot.worlds {
+        let def = &world_snapshot.world;
+        let world_id = WorldId::from(def.id);
+
+        if game.get_world(world_id).is_some() {
+            return Err(SnapshotError::DuplicateWorld(world_id));
+        }
+
+        // A player who is offline may have carried the key of a door out of the world.
+        let problems: Vec<_> = def
+            .validate()
+            .into_iter()
+            .filter(|problem| !matches!(problem, WorldDefError::UnknownKey { .. }))
+            .collect();
+        if !problems.is_empty() {
+            return Err(SnapshotError::InvalidWorld {
+                world: world_id,
+                problems,
+            });
+        }
+
+        let room_ids: HashSet<u32> = def.rooms.iter().map(|r| r.id).collect();
+
+        // The world validation covers the items in the rooms, not the ones characters carry.
+        let mut item_ids = HashSet::new();
+        let room_items = def.rooms.iter().flat_map(|room| room.items.iter());
+        let carried_items = world_snapshot
+            .characters
+            .iter()
+            .flat_map(|c| c.inventory.iter().chain(c.equipment.values()));
+        let mut duplicate_item = None;
+        for item in room_items.chain(carried_items) {
+            item.visit(&mut |item| {
+                if !item_ids.insert(item.id) {
+                    duplicate_item.get_or_insert(item.id);
+                }
+            });
+        }
+        if let Some(id) = duplicate_item {
+            return Err(GameError::DuplicateItem(ItemId::from(id)).into());
+        }
+
+        for char_snapshot in &world_snapshot.characters {
+            if !char_ids.insert((def.id, char_snapshot.id)) {
+                return Err(SnapshotError::DuplicateCharacter(
+                    world_id,
+                    CharacterId::from(char_snapshot.id),
+                ));
+            }
+
+            if let Some(room_id) = char_snapshot.room
+                && !room_ids.contains(&room_id)
+            {
+                return Err(SnapshotError::UnknownRoom(world_id, RoomId::from(room_id)));
+            }
+
+            if let Some(template) = char_snapshot.template
+                && !def.npc_templates.iter().any(|t| t.id == template)
+            {
+                return Err(GameError::UnknownNpcTemplate(template).into());
+            }
+
+            if let Some((slot, item)) = char_snapshot
+                .equipment
+                .iter()
+                .find(|(slot, item)| !item.slots.contains(slot))
+            {
+                let item = ItemId::from(item.id);
+                return Err(GameError::CannotEquip { item, slot: *slot }.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn save_to_file(game: &Game, path: &Path) -> Result<(), SnapshotError> {
+    let text = ron::ser::to_string_pretty(&capture(game), ron::ser::PrettyConfig::default())
+        .map_err(SnapshotError::Serialize)?;
+
+    let io_error = |error| SnapshotError::Io {
+        path: path.to_path_buf(),
+        error,
+    };
+
+    // Write aside and rename so that a crash in the middle never leaves a broken snapshot.
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, text).map_err(io_error)?;
+    fs::rename(&tmp_path, path).map_err(io_error)
+}
+
+pub fn read_file(path: &Path) -> Result<GameSnapshot, SnapshotError> {
+    let text = fs::read_to_string(path).map_err(|error| SnapshotError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+
+    ron::from_str(&text).map_err(|error| SnapshotError::Parse {
+        path: path.to_path_buf(),
+        error: Box::new(error),
+    })
+}
+
+pub fn restore_from_file(game: &mut Game, path: &Path) -> Result<Vec<WorldId>, SnapshotError> {
+    let snapshot = read_file(path)?;
+
+    restore(game, &snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{DoorState, ExitKey, MoveDirection};
+    use crate::item::ItemRef;
+    use std::rc::Rc;
+
+    /// Two rooms behind a door, a bag with a coin and a statue in the hall, and a guard with an
+    /// apple and a helm in the yard.
+    const SNAPSHOT: &str = r#"(
+        worlds: [
+            (
+                world: (
+                    id: 1,
+                    name: "Test",
+                    rooms: [
+                        (
+                            id: 1,
+                            title: "Hall",
+                            exits: {North: 2},
+                            doors: [(exit: "north", name: "gate", state: Closed)],
+                            items: [
+                                (
+                                    id: 1,
+                                    keywords: ["bag"],
+                                    short_description: "a bag",
+                                    flags: [Container],
+                                    contents: [(id: 2, keywords: ["coin"], short_description: "a coin")],
+                                ),
+                            ],
+                        ),
+                        (id: 2, title: "Yard", exits: {South: 1}),
+                    ],
+                    npc_templates: [(id: 1, name: "Guard")],
+                ),
+                characters: [
+                    (
+                        id: 2,
+                        room: Some(1),
+                        name: Some("Statue"),
+                        short_description: "a stone statue",
+                        keywords: ["stone"],
+                    ),
+                    (
+                        id: 1,
+                        room: Some(2),
+                        template: Some(1),
+                        inventory: [(id: 3, keywords: ["apple"], short_description: "an apple")],
+                        equipment: {
+                            Head: (id: 4, keywords: ["helm"], short_description: "a helm", slots: [Head]),
+                        },
+                    ),
+                ],
+            ),
+        ],
+    )"#;
+
+    fn restore_text(text: &str) -> Game {
+        let snapshot: GameSnapshot = ron::from_str(text).expect("the snapshot should parse");
+        let mut game = Game::new();
+        restore(&mut game, &snapshot).expect("the snapshot should restore");
+
+        game
+    }
+
+    fn capture_text(game: &Game) -> String {
+        ron::ser::to_string_pretty(&capture(game), ron::ser::PrettyConfig::default()).unwrap()
+    }
+
+    fn item(world: &World, id: u32) -> ItemRef {
+        world.get_item(ItemId::from(id)).unwrap().clone()
+    }
+
+    #[test]
+    fn round_trip_keeps_the_world() {
+        let game = restore_text(SNAPSHOT);
+        {
+            let world = game.get_world(WorldId::from(1)).unwrap().borrow();
+            let hall = world.get_room(RoomId::from(1)).unwrap();
+            let door = hall.get_door(&ExitKey::from(MoveDirection::North)).unwrap();
+            door.borrow_mut().set_state(DoorState::Locked);
+        }
+
+        let text = capture_text(&game);
+        let restored = restore_text(&text);
+        assert_eq!(capture_text(&restored), text);
+
+        let world = restored.get_world(WorldId::from(1)).unwrap().borrow();
+        let hall = world.get_room(RoomId::from(1)).unwrap();
+        let yard = world.get_room(RoomId::from(2)).unwrap();
+
+        // The exits lead to the rooms of the restored world, not to copies of them.
+        let north = hall.get_exit_target(&ExitKey::from(MoveDirection::North));
+        assert!(Rc::ptr_eq(&north.unwrap().unwrap(), yard));
+        let south = yard.get_exit_target(&ExitKey::from(MoveDirection::South));
+        assert!(Rc::ptr_eq(&south.unwrap().unwrap(), hall));
+
+        let front = hall.get_door(&ExitKey::from(MoveDirection::North)).unwrap();
+        let back = yard.get_door(&ExitKey::from(MoveDirection::South)).unwrap();
+        assert!(Rc::ptr_eq(&front, &back));
+        assert_eq!(back.borrow().get_state(), DoorState::Locked);
+
+        let bag_id = ItemId::from(1);
+        let guard_id = CharacterId::from(1);
+        assert_eq!(
+            item(&world, 1).borrow().get_location(),
+            ItemLocation::Room(RoomId::from(1))
+        );
+        assert_eq!(hall.get_items(), vec![bag_id]);
+        assert_eq!(
+            item(&world, 2).borrow().get_location(),
+            ItemLocation::Container(bag_id)
+        );
+        assert_eq!(
+            item(&world, 3).borrow().get_location(),
+            ItemLocation::Character(guard_id)
+        );
+        assert_eq!(
+            item(&world, 4).borrow().get_location(),
+            ItemLocation::Equipped(guard_id, WearSlot::Head)
+        );
+
+        let guard = world.get_character(guard_id).unwrap().borrow();
+        assert_eq!(guard.get_name(), "Guard");
+        assert_eq!(guard.get_inventory(), [ItemId::from(3)]);
+        assert_eq!(guard.get_equipped(WearSlot::Head), Some(ItemId::from(4)));
+        assert_eq!(yard.get_occupants(), vec![guard_id]);
+
+        let statue = world.get_character(CharacterId::from(2)).unwrap().borrow();
+        assert_eq!(statue.get_short_description(), "a stone statue");
+        assert!(statue.has_keyword("stone"));
+    }
+}
@@ -1,27 +1,222 @@
-use derive_more::From;
+use derive_more::{Display, From, Into};
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::rc::Rc;
 
-use crate::area::Room;
+use crate::area::{Room, Teleportable};
 use crate::game::PlayerRef;
+use crate::item::{ItemId, WearSlot};
 use crate::utils::BuildRef;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, From)]
+/// The most a character can carry, counting the weight of everything inside the containers.
+pub const MAX_CARRY_WEIGHT: u32 = 100;
+
+/// Where the attributes of a new character start.
+pub const BASE_ATTRIBUTE: i32 = 10;
+
+/// The part of the vital maximum which comes back on every regeneration.
+pub const REGENERATION_DIVISOR: i32 = 10;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum Stat {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Armor,
+    Damage,
+}
+
+impl Stat {
+    pub const ALL: [Stat; 6] = [
+        Stat::Strength,
+        Stat::Dexterity,
+        Stat::Constitution,
+        Stat::Intelligence,
+        Stat::Armor,
+        Stat::Damage,
+    ];
+
+    /// The core attributes every character has, unlike the bonuses which come from equipment.
+    pub const ATTRIBUTES: [Stat; 4] = [
+        Stat::Strength,
+        Stat::Dexterity,
+        Stat::Constitution,
+        Stat::Intelligence,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Stat::Strength => "strength",
+            Stat::Dexterity => "dexterity",
+            Stat::Constitution => "constitution",
+            Stat::Intelligence => "intelligence",
+            Stat::Armor => "armor",
+            Stat::Damage => "damage",
+        }
+    }
+}
+
+/// A value for every stat. The ones never set are zero.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Stats {
+    values: BTreeMap<Stat, i32>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, stat: Stat) -> i32 {
+        self.values.get(&stat).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, stat: Stat, value: i32) {
+        self.values.insert(stat, value);
+    }
+
+    pub fn add(&mut self, stat: Stat, delta: i32) {
+        self.set(stat, self.get(stat) + delta);
+    }
+
+    /// The stats which were set, in the order of `Stat`.
+    pub fn iter(&self) -> impl Iterator<Item = (Stat, i32)> + '_ {
+        self.values.iter().map(|(stat, value)| (*stat, *value))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum Vital {
+    HitPoints,
+    Mana,
+    Movement,
+}
+
+impl Vital {
+    pub const ALL: [Vital; 3] = [Vital::HitPoints, Vital::Mana, Vital::Movement];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Vital::HitPoints => "hit points",
+            Vital::Mana => "mana",
+            Vital::Movement => "movement",
+        }
+    }
+
+    /// The maximum a new character starts with.
+    pub fn get_base_max(&self) -> i32 {
+        match self {
+            Vital::HitPoints => 20,
+            Vital::Mana => 10,
+            Vital::Movement => 50,
+        }
+    }
+}
+
+/// How much of a vital is left and how much there can be.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct VitalPool {
+    current: i32,
+    max: i32,
+}
+
+impl VitalPool {
+    /// A full pool.
+    pub fn new(max: i32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn get_current(&self) -> i32 {
+        self.current
+    }
+
+    pub fn get_max(&self) -> i32 {
+        self.max
+    }
+
+    /// Changes the maximum. What is above the new one is lost.
+    pub fn set_max(&mut self, max: i32) {
+        self.max = max;
+        self.current = self.current.min(max);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+
+    /// Adds up to the maximum.
+    pub fn gain(&mut self, amount: i32) {
+        if self.current < self.max {
+            self.current = (self.current + amount).min(self.max);
+        }
+    }
+
+    /// Takes the amount only when there is enough of it.
+    pub fn spend(&mut self, amount: i32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+        self.current -= amount;
+
+        true
+    }
+
+    /// Takes the amount whatever is left, so that hit points can fall below zero.
+    pub fn lose(&mut self, amount: i32) {
+        self.current -= amount;
+    }
+}
+
+#[derive(Debug, Display, PartialEq, Eq, Hash, Clone, Copy, From, Into)]
 pub struct CharacterId(u32);
 
 pub struct Character {
     id: CharacterId,
+    name: Option<String>,
+    short_description: String,
+    keywords: Vec<String>,
+    description: String,
+    template: Option<u32>,
+    race: Option<String>,
+    class: Option<String>,
     owner: Option<PlayerRef>,
     current_room: Option<Rc<Room>>,
+    inventory: Vec<ItemId>,
+    equipment: BTreeMap<WearSlot, ItemId>,
+    base_stats: Stats,
+    vitals: BTreeMap<Vital, VitalPool>,
+    fighting: Option<CharacterId>,
 }
 
 impl Character {
     pub fn new(id: CharacterId) -> Self {
+        let mut base_stats = Stats::new();
+        for stat in Stat::ATTRIBUTES {
+            base_stats.set(stat, BASE_ATTRIBUTE);
+        }
+
         Self {
             id,
+            name: None,
+            short_description: String::new(),
+            keywords: Vec::new(),
+            description: String::new(),
+            template: None,
+            race: None,
+            class: None,
             owner: None,
             current_room: None,
+            inventory: Vec::new(),
+            equipment: BTreeMap::new(),
+            base_stats,
+            vitals: Vital::ALL
+                .iter()
+                .map(|vital| (*vital, VitalPool::new(vital.get_base_max())))
+                .collect(),
+            fighting: None,
         }
     }
 
@@ -29,6 +224,88 @@ impl Character {
         self.id
     }
 
+    pub fn get_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("Character #{}", self.id),
+        }
+    }
+
+    /// Whether the character was given a name rather than going by their id.
+    pub fn has_name(&self) -> bool {
+        self.name.is_some()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    pub fn unset_name(&mut self) {
+        self.name = None;
+    }
+
+    /// A few words about the character besides the name, like `a mangy mutt`. Empty for most
+    /// players.
+    pub fn get_short_description(&self) -> &str {
+        &self.short_description
+    }
+
+    pub fn set_short_description(&mut self, short_description: String) {
+        self.short_description = short_description;
+    }
+
+    /// The words others can call the character by, besides the ones of the name.
+    pub fn get_keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    pub fn set_keywords(&mut self, keywords: Vec<String>) {
+        self.keywords = keywords;
+    }
+
+    pub fn has_keyword(&self, word: &str) -> bool {
+        let name = self.get_name();
+
+        name.eq_ignore_ascii_case(word)
+            || name.split_whitespace().any(|w| w.eq_ignore_ascii_case(word))
+            || self.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+    }
+
+    /// The text shown when someone looks at the character.
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn set_description(&mut self, description: String) {
+        self.description = description;
+    }
+
+    /// The NPC template the character was made from. Players have none.
+    pub fn get_template(&self) -> Option<u32> {
+        self.template
+    }
+
+    pub fn set_template(&mut self, template: u32) {
+        self.template = Some(template);
+    }
+
+    /// The race chosen when the character was created. NPCs have none.
+    pub fn get_race(&self) -> Option<&str> {
+        self.race.as_deref()
+    }
+
+    pub fn set_race(&mut self, race: String) {
+        self.race = Some(race);
+    }
+
+    pub fn get_class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    pub fn set_class(&mut self, class: String) {
+        self.class = Some(class);
+    }
+
     pub fn get_owner(&self) -> &Option<PlayerRef> {
         &self.owner
     }
@@ -52,6 +329,108 @@ impl Character {
     pub fn unset_current_room(&mut self) {
         self.current_room = None;
     }
+
+    pub fn get_inventory(&self) -> &[ItemId] {
+        &self.inventory
+    }
+
+    pub fn has_item(&self, item_id: ItemId) -> bool {
+        self.inventory.contains(&item_id)
+    }
+
+    pub(crate) fn add_to_inventory(&mut self, item_id: ItemId) {
+        if !self.has_item(item_id) {
+            self.inventory.push(item_id);
+        }
+    }
+
+    pub(crate) fn remove_from_inventory(&mut self, item_id: ItemId) {
+        self.inventory.retain(|id| *id != item_id);
+    }
+
+    /// The items the character wears or wields, by slot.
+    pub fn get_equipment(&self) -> &BTreeMap<WearSlot, ItemId> {
+        &self.equipment
+    }
+
+    pub fn get_equipped(&self, slot: WearSlot) -> Option<ItemId> {
+        self.equipment.get(&slot).copied()
+    }
+
+    pub fn is_wearing(&self, item_id: ItemId) -> bool {
+        self.equipment.values().any(|id| *id == item_id)
+    }
+
+    pub(crate) fn equip(&mut self, slot: WearSlot, item_id: ItemId) {
+        self.equipment.insert(slot, item_id);
+    }
+
+    pub(crate) fn unequip(&mut self, slot: WearSlot) {
+        self.equipment.remove(&slot);
+    }
+
+    /// The stats without anything worn. The world adds the equipment to them.
+    pub fn get_base_stats(&self) -> &Stats {
+        &self.base_stats
+    }
+
+    pub fn set_base_stat(&mut self, stat: Stat, value: i32) {
+        self.base_stats.set(stat, value);
+    }
+
+    pub fn get_vital(&self, vital: Vital) -> VitalPool {
+        self.vitals
+            .get(&vital)
+            .copied()
+            .unwrap_or(VitalPool::new(0))
+    }
+
+    pub fn get_vital_mut(&mut self, vital: Vital) -> &mut VitalPool {
+        self.vitals.entry(vital).or_insert(VitalPool::new(0))
+    }
+
+    /// The opponent the character is fighting in the same world.
+    pub fn get_fighting(&self) -> Option<CharacterId> {
+        self.fighting
+    }
+
+    pub fn is_fighting(&self) -> bool {
+        self.fighting.is_some()
+    }
+
+    pub fn set_fighting(&mut self, opponent: CharacterId) {
+        self.fighting = Some(opponent);
+    }
+
+    pub fn unset_fighting(&mut self) {
+        self.fighting = None;
+    }
+
+    /// Brings back a part of every vital which is not full.
+    pub fn regenerate(&mut self) {
+        for pool in self.vitals.values_mut() {
+            pool.gain((pool.get_max() / REGENERATION_DIVISOR).max(1));
+        }
+    }
+}
+
+impl Teleportable for Character {
+    fn move_from_to(&mut self, from: &Rc<Room>, to: &Rc<Room>) -> bool {
+        let is_in_from = match &self.current_room {
+            None => false,
+            Some(r) => Rc::ptr_eq(r, from),
+        };
+
+        if !is_in_from {
+            return false;
+        }
+
+        from.remove_occupant(self.id);
+        to.add_occupant(self.id);
+        self.current_room = Some(to.clone());
+
+        true
+    }
 }
 
 impl PartialEq for Character {
@@ -74,6 +453,10 @@ impl fmt::Debug for Character {
             .field("id", &self.id)
             .field("main_char", &self.owner.as_ref().map(|char| char.borrow().get_id()))
             .field("current_room", &self.current_room)
+            .field("inventory", &self.inventory)
+            .field("equipment", &self.equipment)
+            .field("vitals", &self.vitals)
+            .field("fighting", &self.fighting)
             .finish()
     }
 }
@@ -0,0 +1,333 @@
+use crate::area::{Room, World};
+use crate::item::{ItemId, ItemRef};
+use crate::socium::CharacterRef;
+use std::fmt;
+
+/// Which of the things having the keyword a target picks.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Selector {
+    /// The nth of them, counting from one.
+    Nth(usize),
+    All,
+}
+
+/// What a player types to point at characters or items: `goblin` or `2.goblin` picks one of
+/// them, `all.coin` every coin and `all` everything.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Target {
+    selector: Selector,
+    /// No keyword matches anything, which only `all` allows.
+    keyword: Option<String>,
+}
+
+impl Target {
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        if input.eq_ignore_ascii_case("all") {
+            return Self {
+                selector: Selector::All,
+                keyword: None,
+            };
+        }
+
+        let (selector, keyword) = match input.split_once('.') {
+            Some((prefix, keyword)) if !keyword.is_empty() => {
+                match (prefix.to_lowercase().as_str(), prefix.parse::<usize>()) {
+                    ("all", _) => (Selector::All, keyword),
+                    (_, Ok(n)) => (Selector::Nth(n), keyword),
+                    _ => (Selector::Nth(1), input),
+                }
+            }
+            _ => (Selector::Nth(1), input),
+        };
+
+        Self {
+            selector,
+            keyword: Some(keyword.to_string()),
+        }
+    }
+
+    pub fn get_selector(&self) -> Selector {
+        self.selector
+    }
+
+    pub fn get_keyword(&self) -> Option<&str> {
+        self.keyword.as_deref()
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.selector == Selector::All
+    }
+
+    /// Keeps the candidates the target picks, in their order.
+    fn select<T>(
+        &self,
+        candidates: impl Iterator<Item = T>,
+        has_keyword: impl Fn(&T, &str) -> bool,
+    ) -> Vec<T> {
+        let mut matching = candidates.filter(|candidate| match &self.keyword {
+            Some(keyword) => has_keyword(candidate, keyword),
+            None => true,
+        });
+
+        match self.selector {
+            Selector::All => matching.collect(),
+            Selector::Nth(n) => n
+                .checked_sub(1)
+                .and_then(|skip| matching.nth(skip))
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Writes the keyword, as in `You see no goblin here.`
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.keyword {
+            Some(keyword) => write!(f, "{}", keyword),
+            None => write!(f, "such thing"),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Items
+// ----------------------------------------------------------------------------------------------------
+
+/// Finds the items the target picks among the ones listed, like the inventory or the room.
+pub fn find_items(world: &World, item_ids: &[ItemId], target: &Target) -> Vec<ItemRef> {
+    let candidates = item_ids
+        .iter()
+        .filter_map(|id| world.get_item(*id))
+        .cloned();
+
+    target.select(candidates, |item, keyword| {
+        item.borrow().has_keyword(keyword)
+    })
+}
+
+/// Finds the item the target picks among the ones listed. `all` picks the first one.
+pub fn find_item(world: &World, item_ids: &[ItemId], target: &Target) -> Option<ItemRef> {
+    find_items(world, item_ids, target).into_iter().next()
+}
+
+/// Finds the item the target picks wherever it is in the world.
+pub fn find_item_in_world(world: &World, target: &Target) -> Option<ItemRef> {
+    let mut item_ids: Vec<ItemId> = world
+        .get_items()
+        .map(|item| item.borrow().get_id())
+        .collect();
+    item_ids.sort_by_key(|id| u32::from(*id));
+
+    find_item(world, &item_ids, target)
+}
+
+// ----------------------------------------------------------------------------------------------------
+// Characters
+// ----------------------------------------------------------------------------------------------------
+
+/// Finds the characters the target picks among the ones listed.
+pub fn find_characters(chars: Vec<CharacterRef>, target: &Target) -> Vec<CharacterRef> {
+    target.select(chars.into_iter(), |char, keyword| {
+        char.borrow().has_keyword(keyword)
+    })
+}
+
+/// Finds the character the target picks in the room.
+pub fn find_character(world: &World, room: &Room, target: &Target) -> Option<CharacterRef> {
+    find_characters(world.get_characters_in_room(room), target)
+        .into_iter()
+        .next()
+}
+
+/// Finds the character the target picks wherever they are in the world.
+pub fn find_character_in_world(world: &World, target: &Target) -> Option<CharacterRef> {
+    let mut chars: Vec<CharacterRef> = world.get_characters().cloned().collect();
+    chars.sort_by_key(|char| u32::from(char.borrow().get_id()));
+
+    find_characters(chars, target).into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::{RoomId, WorldId};
+    use crate::item::{Item, ItemLocation};
+    use crate::socium::{Character, CharacterId};
+    use crate::utils::BuildRef;
+    use std::rc::Rc;
+
+    /// Coins among other things. `coins` shows that a keyword has to match a whole one.
+    fn items() -> (World, Vec<ItemId>) {
+        let mut world = World::new(WorldId::from(1), String::from("Test"));
+        let mut ids = Vec::new();
+        for (keywords, short) in [
+            (&["coin", "gold"][..], "a gold coin"),
+            (&["key"][..], "a key"),
+            (&["coin", "copper"][..], "a copper coin"),
+            (&["coins"][..], "a pile of coins"),
+            (&["coin", "silver"][..], "a silver coin"),
+        ] {
+            let item = Item::new(
+                world.allocate_item_id(),
+                keywords.iter().map(|k| k.to_string()).collect(),
+                short.to_string(),
+            );
+            ids.push(world.add_item(item, ItemLocation::Nowhere).unwrap());
+        }
+
+        (world, ids)
+    }
+
+    /// The short descriptions of the items the input picks.
+    fn pick(input: &str) -> Vec<String> {
+        let (world, ids) = items();
+
+        find_items(&world, &ids, &Target::parse(input))
+            .into_iter()
+            .map(|item| item.borrow().get_short_description().to_string())
+            .collect()
+    }
+
+    /// The names of the goblins the input picks.
+    fn pick_goblins(input: &str) -> Vec<String> {
+        let chars: Vec<CharacterRef> =
+            [("Grik", "goblin"), ("Bob", "human"), ("Old Snag", "goblin")]
+                .into_iter()
+                .enumerate()
+                .map(|(id, (name, keyword))| {
+                    let mut char = Character::new(CharacterId::from(id as u32 + 1));
+                    char.set_name(String::from(name));
+                    char.set_keywords(vec![String::from(keyword)]);
+                    char.build_ref()
+                })
+                .collect();
+
+        find_characters(chars, &Target::parse(input))
+            .into_iter()
+            .map(|char| char.borrow().get_name())
+            .collect()
+    }
+
+    #[test]
+    fn plain_keyword_picks_the_first_match() {
+        let target = Target::parse("coin");
+        assert_eq!(target.get_selector(), Selector::Nth(1));
+        assert_eq!(target.get_keyword(), Some("coin"));
+
+        assert_eq!(pick("coin"), ["a gold coin"]);
+        assert_eq!(pick("COPPER"), ["a copper coin"]);
+        assert_eq!(pick_goblins("goblin"), ["Grik"]);
+    }
+
+    #[test]
+    fn ordinal_picks_the_nth_match() {
+        assert_eq!(pick("2.coin"), ["a copper coin"]);
+        assert_eq!(pick("3.coin"), ["a silver coin"]);
+        assert!(pick("4.coin").is_empty());
+
+        assert_eq!(pick_goblins("2.goblin"), ["Old Snag"]);
+        assert!(pick_goblins("3.goblin").is_empty());
+    }
+
+    #[test]
+    fn zero_ordinal_picks_nothing() {
+        assert_eq!(Target::parse("0.coin").get_selector(), Selector::Nth(0));
+        assert!(pick("0.coin").is_empty());
+    }
+
+    #[test]
+    fn all_prefix_picks_every_match() {
+        let target = Target::parse("ALL.coin");
+        assert!(target.is_all());
+        assert_eq!(target.get_keyword(), Some("coin"));
+
+        assert_eq!(
+            pick("all.coin"),
+            ["a gold coin", "a copper coin", "a silver coin"]
+        );
+        assert_eq!(pick_goblins("all.goblin"), ["Grik", "Old Snag"]);
+    }
+
+    #[test]
+    fn bare_all_picks_everything() {
+        let target = Target::parse("all");
+        assert!(target.is_all());
+        assert_eq!(target.get_keyword(), None);
+        assert_eq!(target.to_string(), "such thing");
+
+        assert_eq!(pick("all").len(), items().1.len());
+    }
+
+    #[test]
+    fn characters_are_picked_by_the_words_of_their_name() {
+        assert_eq!(pick_goblins("snag"), ["Old Snag"]);
+        assert_eq!(pick_goblins("old snag"), ["Old Snag"]);
+        assert_eq!(pick_goblins("all.bob"), ["Bob"]);
+    }
+
+    #[test]
+    fn unknown_keyword_picks_nothing() {
+        assert!(pick("sword").is_empty());
+        assert!(pick("all.sword").is_empty());
+        assert!(pick("coi").is_empty());
+        assert!(pick_goblins("gob").is_empty());
+    }
+
+    #[test]
+    fn dot_without_ordinal_is_part_of_the_keyword() {
+        assert_eq!(Target::parse("2.").get_keyword(), Some("2."));
+        assert_eq!(Target::parse("x.coin").get_keyword(), Some("x.coin"));
+    }
+
+    #[test]
+    fn first_found_item_is_the_first_pick() {
+        let (world, ids) = items();
+
+        let first = find_item(&world, &ids, &Target::parse("all.coin"));
+        assert_eq!(
+            first.map(|item| item.borrow().get_short_description().to_string()),
+            Some(String::from("a gold coin"))
+        );
+        assert!(find_item(&world, &ids, &Target::parse("sword")).is_none());
+    }
+
+    #[test]
+    fn world_finders_look_in_every_room() {
+        let world = World::new(WorldId::from(1), String::from("Test")).build_ref();
+        let rooms: Vec<Rc<Room>> = (1..=2)
+            .map(|id| Rc::new(Room::new_in_void(RoomId::from(id), world.clone())))
+            .collect();
+        let mut world_mut = world.borrow_mut();
+        for room in &rooms {
+            world_mut.add_room(room.clone());
+        }
+
+        for (room, name) in rooms.iter().zip(["Grik", "Snag"]) {
+            let mut goblin = Character::new(world_mut.allocate_character_id());
+            goblin.set_name(String::from(name));
+            goblin.set_keywords(vec![String::from("goblin")]);
+            let goblin = world_mut.add_character(goblin).unwrap();
+            world_mut.teleport_character(goblin, room);
+
+            let coin = Item::new(
+                world_mut.allocate_item_id(),
+                vec![String::from("coin")],
+                format!("{}'s coin", name),
+            );
+            world_mut
+                .add_item(coin, ItemLocation::Room(room.get_id()))
+                .unwrap();
+        }
+        drop(world_mut);
+        let world_ref = world.borrow();
+
+        let goblin = find_character_in_world(&world_ref, &Target::parse("2.goblin")).unwrap();
+        assert_eq!(goblin.borrow().get_name(), "Snag");
+        let coin = find_item_in_world(&world_ref, &Target::parse("2.coin")).unwrap();
+        assert_eq!(coin.borrow().get_short_description(), "Snag's coin");
+        assert!(find_character_in_world(&world_ref, &Target::parse("3.goblin")).is_none());
+        assert!(find_item_in_world(&world_ref, &Target::parse("sword")).is_none());
+    }
+}